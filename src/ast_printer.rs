@@ -0,0 +1,380 @@
+use crate::expression::{
+    self, Assign, Binary, Call, Expression, Get, Grouping, Index, Interpolation, Lambda, List,
+    Logical, Map, Set, SetIndex, Super, This, Unary, Variable,
+};
+use crate::scanner::{self, Token};
+use crate::statement::{
+    self, Block, Break, Class, Continue, DoWhile, Function, If, Print, Record, Return, Statement,
+    Switch, Var, While,
+};
+
+/* Renders a syntax tree as Lisp-style text, such as (print (+ 1 2)). Statements
+that contain other statements put each child on its own line, indented one level
+deeper than the parent. */
+pub struct AstPrinter;
+
+impl AstPrinter {
+    pub fn new() -> Self {
+        AstPrinter
+    }
+
+    pub fn print(&mut self, statement: &dyn Statement) -> String {
+        statement.accept(self)
+    }
+
+    fn expression(&mut self, expression: &dyn Expression) -> String {
+        expression.accept(self)
+    }
+
+    // Wrap the name and the rendered expressions in parentheses.
+    fn parenthesize(&mut self, name: &str, expressions: Vec<&dyn Expression>) -> String {
+        let mut text = format!("({}", name);
+        for expression in expressions {
+            text.push(' ');
+            text.push_str(&self.expression(expression));
+        }
+        text.push(')');
+        text
+    }
+
+    // The operator of a compound assignment such as +=, which goes in front of the '='.
+    fn compound(&self, operator: &Option<Token>) -> String {
+        match operator {
+            Some(operator) => operator.lexeme.clone(),
+            None => String::new(),
+        }
+    }
+
+    // Put each statement on its own line below the head, then close the parenthesis.
+    fn nest(&mut self, head: String, statements: &[Box<dyn Statement>]) -> String {
+        let mut text = head;
+        for statement in statements {
+            text.push('\n');
+            text.push_str(&self.indent(statement.as_ref()));
+        }
+        text.push(')');
+        text
+    }
+
+    // Render the statement one level deeper than the current line.
+    fn indent(&mut self, statement: &dyn Statement) -> String {
+        let text = self.print(statement);
+        self.indent_text(&text)
+    }
+
+    // Move every line of already rendered text one level deeper.
+    fn indent_text(&self, text: &str) -> String {
+        text.lines()
+            .map(|line| format!("  {}", line))
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+}
+
+impl expression::Visitor<String> for AstPrinter {
+    fn visit_assign_expression(&mut self, expression: &Assign) -> String {
+        let name = format!("= {}", expression.name.lexeme);
+        self.parenthesize(&name, vec![expression.value.as_ref()])
+    }
+
+    fn visit_binary_expression(&mut self, expression: &Binary) -> String {
+        self.parenthesize(
+            &expression.operator.lexeme,
+            vec![expression.left.as_ref(), expression.right.as_ref()],
+        )
+    }
+
+    fn visit_call_expression(&mut self, expression: &Call) -> String {
+        let mut expressions = vec![expression.callee.as_ref()];
+        for argument in &expression.arguments {
+            expressions.push(argument.as_ref());
+        }
+        self.parenthesize("call", expressions)
+    }
+
+    fn visit_get_expression(&mut self, expression: &Get) -> String {
+        let object = self.expression(expression.object.as_ref());
+        format!("(. {} {})", object, expression.name.lexeme)
+    }
+
+    fn visit_grouping_expression(&mut self, expression: &Grouping) -> String {
+        self.parenthesize("group", vec![expression.expression.as_ref()])
+    }
+
+    fn visit_index_expression(&mut self, expression: &Index) -> String {
+        self.parenthesize(
+            "[]",
+            vec![expression.object.as_ref(), expression.index.as_ref()],
+        )
+    }
+
+    fn visit_interpolation_expression(&mut self, expression: &Interpolation) -> String {
+        let parts = expression.parts.iter().map(|part| part.as_ref()).collect();
+        self.parenthesize("interpolate", parts)
+    }
+
+    fn visit_lambda_expression(&mut self, expression: &Lambda) -> String {
+        let params: Vec<&str> = expression
+            .params
+            .iter()
+            .map(|param| param.lexeme.as_str())
+            .collect();
+        self.nest(format!("(fun ({})", params.join(" ")), &expression.body)
+    }
+
+    fn visit_list_expression(&mut self, expression: &List) -> String {
+        let elements = expression
+            .elements
+            .iter()
+            .map(|element| element.as_ref())
+            .collect();
+        self.parenthesize("list", elements)
+    }
+
+    fn visit_map_expression(&mut self, expression: &Map) -> String {
+        let mut entries = Vec::new();
+        for (key, value) in &expression.entries {
+            entries.push(key.as_ref());
+            entries.push(value.as_ref());
+        }
+        self.parenthesize("map", entries)
+    }
+
+    fn visit_literal_expression(&mut self, expression: &expression::Literal) -> String {
+        match &expression.value {
+            scanner::Literal::Bool(value) => value.to_string(),
+            scanner::Literal::Number(value) => value.to_string(),
+            scanner::Literal::String(value) => format!("\"{}\"", value),
+            scanner::Literal::Nil => String::from("nil"),
+            _ => unreachable!("Only scanned values appear in literal expressions."),
+        }
+    }
+
+    fn visit_logical_expression(&mut self, expression: &Logical) -> String {
+        self.parenthesize(
+            &expression.operator.lexeme,
+            vec![expression.left.as_ref(), expression.right.as_ref()],
+        )
+    }
+
+    fn visit_set_expression(&mut self, expression: &Set) -> String {
+        let object = self.expression(expression.object.as_ref());
+        let value = self.expression(expression.value.as_ref());
+        format!(
+            "({}= (. {} {}) {})",
+            self.compound(&expression.operator),
+            object,
+            expression.name.lexeme,
+            value
+        )
+    }
+
+    fn visit_set_index_expression(&mut self, expression: &SetIndex) -> String {
+        let object = self.expression(expression.object.as_ref());
+        let index = self.expression(expression.index.as_ref());
+        let value = self.expression(expression.value.as_ref());
+        format!(
+            "({}= ([] {} {}) {})",
+            self.compound(&expression.operator),
+            object,
+            index,
+            value
+        )
+    }
+
+    fn visit_super_expression(&mut self, expression: &Super) -> String {
+        format!("(super {})", expression.method.lexeme)
+    }
+
+    fn visit_this_expression(&mut self, _expression: &This) -> String {
+        String::from("this")
+    }
+
+    fn visit_unary_expression(&mut self, expression: &Unary) -> String {
+        self.parenthesize(&expression.operator.lexeme, vec![expression.right.as_ref()])
+    }
+
+    fn visit_variable_expression(&mut self, expression: &Variable) -> String {
+        expression.name.lexeme.clone()
+    }
+}
+
+impl statement::Visitor<String> for AstPrinter {
+    fn visit_block_statement(&mut self, statement: &Block) -> String {
+        self.nest(String::from("(block"), &statement.statements)
+    }
+
+    fn visit_break_statement(&mut self, _statement: &Break) -> String {
+        String::from("(break)")
+    }
+
+    fn visit_class_statement(&mut self, statement: &Class) -> String {
+        let mut text = format!("(class {}", statement.name.lexeme);
+        if let Some(superclass) = &statement.superclass {
+            text.push_str(&format!(" < {}", superclass.name.lexeme));
+        }
+        for method in &statement.methods {
+            text.push('\n');
+            text.push_str(&self.indent(method));
+        }
+        text.push(')');
+        text
+    }
+
+    fn visit_continue_statement(&mut self, _statement: &Continue) -> String {
+        String::from("(continue)")
+    }
+
+    // The condition is shown after the body, which is when it's first checked.
+    fn visit_do_while_statement(&mut self, statement: &DoWhile) -> String {
+        let body = self.indent(statement.body.as_ref());
+        let condition = self.expression(statement.condition.as_ref());
+        format!("(do\n{}\n  (while {}))", body, condition)
+    }
+
+    fn visit_expression_statement(&mut self, statement: &statement::Expression) -> String {
+        self.parenthesize(";", vec![statement.expression.as_ref()])
+    }
+
+    fn visit_function_statement(&mut self, statement: &Function) -> String {
+        let params: Vec<&str> = statement
+            .params
+            .iter()
+            .map(|param| param.lexeme.as_str())
+            .collect();
+        let mut head = format!("(fun {} ({})", statement.name.lexeme, params.join(" "));
+        if let Some(guard) = &statement.guard {
+            head.push_str(" when ");
+            head.push_str(&self.expression(guard.as_ref()));
+        }
+        self.nest(head, &statement.body)
+    }
+
+    /* An else branch that is itself an if statement is shown as "else if" at the
+    same level, so a long chain of conditions doesn't drift further to the right. */
+    fn visit_if_statement(&mut self, statement: &If) -> String {
+        let condition = self.expression(statement.condition.as_ref());
+        let mut text = format!(
+            "(if {}\n{}",
+            condition,
+            self.indent(statement.then_branch.as_ref())
+        );
+
+        let mut else_branch = statement.else_branch.as_ref();
+        while let Some(branch) = else_branch {
+            match branch.as_any().downcast_ref::<If>() {
+                Some(else_if) => {
+                    let condition = self.expression(else_if.condition.as_ref());
+                    text.push_str(&format!("\nelse if {}\n", condition));
+                    text.push_str(&self.indent(else_if.then_branch.as_ref()));
+                    else_branch = else_if.else_branch.as_ref();
+                }
+                None => {
+                    text.push_str("\nelse\n");
+                    text.push_str(&self.indent(branch.as_ref()));
+                    else_branch = None;
+                }
+            }
+        }
+
+        text.push(')');
+        text
+    }
+
+    fn visit_print_statement(&mut self, statement: &Print) -> String {
+        self.parenthesize("print", vec![statement.expression.as_ref()])
+    }
+
+    fn visit_record_statement(&mut self, statement: &Record) -> String {
+        let fields: Vec<&str> = statement
+            .fields
+            .iter()
+            .map(|field| field.lexeme.as_str())
+            .collect();
+        format!("(record {} ({}))", statement.name.lexeme, fields.join(" "))
+    }
+
+    fn visit_return_statement(&mut self, statement: &Return) -> String {
+        match &statement.value {
+            Some(value) => self.parenthesize("return", vec![value.as_ref()]),
+            None => String::from("(return)"),
+        }
+    }
+
+    // Each case is shown like a nested statement, with its body indented below it.
+    fn visit_switch_statement(&mut self, statement: &Switch) -> String {
+        let subject = self.expression(statement.subject.as_ref());
+        let mut text = format!("(switch {}", subject);
+        for case in &statement.cases {
+            let value = self.expression(case.value.as_ref());
+            let case = self.nest(format!("(case {}", value), &case.body);
+            text.push('\n');
+            text.push_str(&self.indent_text(&case));
+        }
+        if let Some(default) = &statement.default {
+            let default = self.nest(String::from("(default"), default);
+            text.push('\n');
+            text.push_str(&self.indent_text(&default));
+        }
+        text.push(')');
+        text
+    }
+
+    fn visit_var_statement(&mut self, statement: &Var) -> String {
+        let name = format!("var {}", statement.name.lexeme);
+        match &statement.initializer {
+            Some(initializer) => self.parenthesize(&name, vec![initializer.as_ref()]),
+            None => format!("({})", name),
+        }
+    }
+
+    fn visit_while_statement(&mut self, statement: &While) -> String {
+        // The increments of a for loop are shown after the body, which is when they run.
+        let condition = self.expression(statement.condition.as_ref());
+        let mut text = format!(
+            "(while {}\n{}",
+            condition,
+            self.indent(statement.body.as_ref())
+        );
+        if !statement.increments.is_empty() {
+            let increments = statement
+                .increments
+                .iter()
+                .map(|increment| increment.as_ref())
+                .collect();
+            let increments = self.parenthesize("increment", increments);
+            text.push_str(&format!("\n  {}", increments));
+        }
+        text.push(')');
+        text
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::AstPrinter;
+    use crate::testing::parse;
+
+    // Print each statement of the source on its own line.
+    fn print(source: &str) -> String {
+        let mut printer = AstPrinter::new();
+        let lines: Vec<String> = parse(source)
+            .iter()
+            .map(|statement| printer.print(statement.as_ref()))
+            .collect();
+        lines.join("\n")
+    }
+
+    #[test]
+    fn else_if_chain_stays_flat() {
+        let source = "if (a) print 1; else if (b) print 2; else print 3;";
+        let expected = "(if a\n  (print 1)\nelse if b\n  (print 2)\nelse\n  (print 3))";
+        assert_eq!(print(source), expected);
+    }
+
+    #[test]
+    fn and_binds_tighter_than_or() {
+        let source = "print a or b and c == d;";
+        assert_eq!(print(source), "(print (or a (and b (== c d))))");
+    }
+}
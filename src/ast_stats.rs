@@ -0,0 +1,301 @@
+use std::cmp::max;
+use std::collections::BTreeMap;
+use std::fmt;
+
+use crate::expression::{
+    self, Assign, Binary, Call, Expression, Get, Grouping, Index, Interpolation, Lambda, List,
+    Logical, Map, Set, SetIndex, Super, This, Unary, Variable,
+};
+use crate::statement::{
+    self, Block, Break, Class, Continue, DoWhile, Function, If, Print, Record, Return, Statement,
+    Switch, Var, While,
+};
+
+/* Counts how many of each kind of node a syntax tree has, and how deep it goes.
+Each visit returns the depth of the subtree rooted at the node. */
+pub struct AstStats {
+    counts: BTreeMap<&'static str, usize>, // Sorted so the report is in a stable order.
+    max_depth: usize,
+}
+
+impl AstStats {
+    pub fn new() -> Self {
+        AstStats {
+            counts: BTreeMap::new(),
+            max_depth: 0,
+        }
+    }
+
+    // Add the nodes of the program's statements to the counts.
+    pub fn count(&mut self, statements: &[Box<dyn Statement>]) {
+        let depth = self.statements(statements);
+        self.max_depth = max(self.max_depth, depth);
+    }
+
+    // Count a node whose deepest child subtree is the given depth.
+    fn node(&mut self, kind: &'static str, children_depth: usize) -> usize {
+        *self.counts.entry(kind).or_insert(0) += 1;
+        children_depth + 1
+    }
+
+    fn expression(&mut self, expression: &dyn Expression) -> usize {
+        expression.accept(self)
+    }
+
+    fn statement(&mut self, statement: &dyn Statement) -> usize {
+        statement.accept(self)
+    }
+
+    // Count a list of sibling statements, returning the depth of the deepest one.
+    fn statements(&mut self, statements: &[Box<dyn Statement>]) -> usize {
+        statements
+            .iter()
+            .map(|statement| self.statement(statement.as_ref()))
+            .max()
+            .unwrap_or(0)
+    }
+}
+
+impl fmt::Display for AstStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (kind, count) in &self.counts {
+            writeln!(f, "{} {}", kind, count)?;
+        }
+
+        writeln!(f, "Total {}", self.counts.values().sum::<usize>())?;
+        writeln!(f, "Max depth {}", self.max_depth)
+    }
+}
+
+impl expression::Visitor<usize> for AstStats {
+    fn visit_assign_expression(&mut self, expression: &Assign) -> usize {
+        let depth = self.expression(expression.value.as_ref());
+        self.node("Assign", depth)
+    }
+
+    fn visit_binary_expression(&mut self, expression: &Binary) -> usize {
+        let left = self.expression(expression.left.as_ref());
+        let right = self.expression(expression.right.as_ref());
+        self.node("Binary", max(left, right))
+    }
+
+    fn visit_call_expression(&mut self, expression: &Call) -> usize {
+        let mut depth = self.expression(expression.callee.as_ref());
+        for argument in &expression.arguments {
+            depth = max(depth, self.expression(argument.as_ref()));
+        }
+        self.node("Call", depth)
+    }
+
+    fn visit_get_expression(&mut self, expression: &Get) -> usize {
+        let depth = self.expression(expression.object.as_ref());
+        self.node("Get", depth)
+    }
+
+    fn visit_grouping_expression(&mut self, expression: &Grouping) -> usize {
+        let depth = self.expression(expression.expression.as_ref());
+        self.node("Grouping", depth)
+    }
+
+    fn visit_index_expression(&mut self, expression: &Index) -> usize {
+        let object = self.expression(expression.object.as_ref());
+        let index = self.expression(expression.index.as_ref());
+        self.node("Index", max(object, index))
+    }
+
+    fn visit_interpolation_expression(&mut self, expression: &Interpolation) -> usize {
+        let depth = expression
+            .parts
+            .iter()
+            .map(|part| self.expression(part.as_ref()))
+            .max()
+            .unwrap_or(0);
+        self.node("Interpolation", depth)
+    }
+
+    fn visit_lambda_expression(&mut self, expression: &Lambda) -> usize {
+        let depth = self.statements(&expression.body);
+        self.node("Lambda", depth)
+    }
+
+    fn visit_list_expression(&mut self, expression: &List) -> usize {
+        let depth = expression
+            .elements
+            .iter()
+            .map(|element| self.expression(element.as_ref()))
+            .max()
+            .unwrap_or(0);
+        self.node("List", depth)
+    }
+
+    fn visit_literal_expression(&mut self, _expression: &expression::Literal) -> usize {
+        self.node("Literal", 0)
+    }
+
+    fn visit_logical_expression(&mut self, expression: &Logical) -> usize {
+        let left = self.expression(expression.left.as_ref());
+        let right = self.expression(expression.right.as_ref());
+        self.node("Logical", max(left, right))
+    }
+
+    fn visit_map_expression(&mut self, expression: &Map) -> usize {
+        let mut depth = 0;
+        for (key, value) in &expression.entries {
+            depth = max(
+                depth,
+                max(
+                    self.expression(key.as_ref()),
+                    self.expression(value.as_ref()),
+                ),
+            );
+        }
+        self.node("Map", depth)
+    }
+
+    fn visit_set_expression(&mut self, expression: &Set) -> usize {
+        let object = self.expression(expression.object.as_ref());
+        let value = self.expression(expression.value.as_ref());
+        self.node("Set", max(object, value))
+    }
+
+    fn visit_set_index_expression(&mut self, expression: &SetIndex) -> usize {
+        let object = self.expression(expression.object.as_ref());
+        let index = self.expression(expression.index.as_ref());
+        let value = self.expression(expression.value.as_ref());
+        self.node("SetIndex", max(max(object, index), value))
+    }
+
+    fn visit_super_expression(&mut self, _expression: &Super) -> usize {
+        self.node("Super", 0)
+    }
+
+    fn visit_this_expression(&mut self, _expression: &This) -> usize {
+        self.node("This", 0)
+    }
+
+    fn visit_unary_expression(&mut self, expression: &Unary) -> usize {
+        let depth = self.expression(expression.right.as_ref());
+        self.node("Unary", depth)
+    }
+
+    fn visit_variable_expression(&mut self, _expression: &Variable) -> usize {
+        self.node("Variable", 0)
+    }
+}
+
+impl statement::Visitor<usize> for AstStats {
+    fn visit_block_statement(&mut self, statement: &Block) -> usize {
+        let depth = self.statements(&statement.statements);
+        self.node("Block", depth)
+    }
+
+    fn visit_break_statement(&mut self, _statement: &Break) -> usize {
+        self.node("Break", 0)
+    }
+
+    fn visit_class_statement(&mut self, statement: &Class) -> usize {
+        let mut depth = statement
+            .methods
+            .iter()
+            .map(|method| self.statement(method))
+            .max()
+            .unwrap_or(0);
+        if let Some(superclass) = &statement.superclass {
+            depth = max(depth, self.expression(superclass));
+        }
+        self.node("Class", depth)
+    }
+
+    fn visit_continue_statement(&mut self, _statement: &Continue) -> usize {
+        self.node("Continue", 0)
+    }
+
+    fn visit_do_while_statement(&mut self, statement: &DoWhile) -> usize {
+        let body = self.statement(statement.body.as_ref());
+        let condition = self.expression(statement.condition.as_ref());
+        self.node("DoWhile", max(body, condition))
+    }
+
+    fn visit_expression_statement(&mut self, statement: &statement::Expression) -> usize {
+        let depth = self.expression(statement.expression.as_ref());
+        self.node("Expression", depth)
+    }
+
+    fn visit_function_statement(&mut self, statement: &Function) -> usize {
+        let mut depth = self.statements(&statement.body);
+        if let Some(guard) = &statement.guard {
+            depth = max(depth, self.expression(guard.as_ref()));
+        }
+        self.node("Function", depth)
+    }
+
+    fn visit_if_statement(&mut self, statement: &If) -> usize {
+        let mut depth = self.expression(statement.condition.as_ref());
+        depth = max(depth, self.statement(statement.then_branch.as_ref()));
+        if let Some(else_branch) = &statement.else_branch {
+            depth = max(depth, self.statement(else_branch.as_ref()));
+        }
+        self.node("If", depth)
+    }
+
+    fn visit_print_statement(&mut self, statement: &Print) -> usize {
+        let depth = self.expression(statement.expression.as_ref());
+        self.node("Print", depth)
+    }
+
+    fn visit_record_statement(&mut self, _statement: &Record) -> usize {
+        self.node("Record", 0)
+    }
+
+    fn visit_return_statement(&mut self, statement: &Return) -> usize {
+        let depth = match &statement.value {
+            Some(value) => self.expression(value.as_ref()),
+            None => 0,
+        };
+        self.node("Return", depth)
+    }
+
+    fn visit_switch_statement(&mut self, statement: &Switch) -> usize {
+        let mut depth = self.expression(statement.subject.as_ref());
+        for case in &statement.cases {
+            depth = max(depth, self.expression(case.value.as_ref()));
+            depth = max(depth, self.statements(&case.body));
+        }
+        if let Some(default) = &statement.default {
+            depth = max(depth, self.statements(default));
+        }
+        self.node("Switch", depth)
+    }
+
+    fn visit_var_statement(&mut self, statement: &Var) -> usize {
+        let depth = match &statement.initializer {
+            Some(initializer) => self.expression(initializer.as_ref()),
+            None => 0,
+        };
+        self.node("Var", depth)
+    }
+
+    fn visit_while_statement(&mut self, statement: &While) -> usize {
+        let condition = self.expression(statement.condition.as_ref());
+        let mut depth = max(condition, self.statement(statement.body.as_ref()));
+        for increment in &statement.increments {
+            depth = max(depth, self.expression(increment.as_ref()));
+        }
+        self.node("While", depth)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::AstStats;
+    use crate::testing::parse;
+
+    #[test]
+    fn counts_nodes_by_kind() {
+        let mut stats = AstStats::new();
+        stats.count(&parse("var a = 1 + 2;\nif (a > 2) print a;"));
+        let expected =
+            "Binary 2\nIf 1\nLiteral 3\nPrint 1\nVar 1\nVariable 2\nTotal 10\nMax depth 3\n";
+        assert_eq!(stats.to_string(), expected);
+    }
+}
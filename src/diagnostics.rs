@@ -0,0 +1,136 @@
+use crate::scanner::{Token, TokenType};
+
+// The stage that found an error, in the order they run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Phase {
+    Scan,
+    Parse,
+    Resolve,
+}
+
+// A single error found before the program runs.
+pub struct Diagnostic {
+    pub phase: Phase,
+    pub line: u32,
+    pub column: u32,
+    pub location: String, // Such as "at 'x'", or empty when there's no token to point at.
+    pub message: String,
+}
+
+/* Every error found while scanning, parsing and resolving a source, so that a single
+run can report all of them at once instead of only the first. */
+pub struct Diagnostics {
+    source_name: String, // Where the source came from, such as the script's path.
+    lines: Vec<String>,  // The source itself, so errors can show the line they're on.
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl Diagnostics {
+    pub fn new(source_name: String, source: &str) -> Self {
+        Diagnostics {
+            source_name,
+            lines: source.lines().map(String::from).collect(),
+            diagnostics: Vec::new(),
+        }
+    }
+
+    // Record an error at a position in the source that isn't a whole token.
+    pub fn error(&mut self, phase: Phase, line: u32, column: u32, message: String) {
+        self.diagnostics.push(Diagnostic {
+            phase,
+            line,
+            column,
+            location: String::new(),
+            message,
+        });
+    }
+
+    // Record an error at the given token.
+    pub fn token_error(&mut self, phase: Phase, token: &Token, message: String) {
+        let location = if token.token_type == TokenType::EOF {
+            String::from("at end")
+        } else {
+            format!("at '{}'", token.lexeme)
+        };
+        self.diagnostics.push(Diagnostic {
+            phase,
+            line: token.line,
+            column: token.column,
+            location,
+            message,
+        });
+    }
+
+    pub fn has_errors(&self) -> bool {
+        !self.diagnostics.is_empty()
+    }
+
+    pub fn source_name(&self) -> &str {
+        &self.source_name
+    }
+
+    /* The errors in the order they appear in the source rather than the order they
+    were found. Errors at the same place are in the order of their phases. */
+    pub fn sorted(&self) -> Vec<&Diagnostic> {
+        let mut diagnostics: Vec<&Diagnostic> = self.diagnostics.iter().collect();
+        diagnostics
+            .sort_by_key(|diagnostic| (diagnostic.line, diagnostic.column, diagnostic.phase));
+        diagnostics
+    }
+
+    /* The source line with a caret under the given column, like rustc shows. Tabs
+    are kept in front of the caret so it still lines up. None when the line isn't
+    in the source, as with a '//#line' directive or an error at the very end. */
+    pub fn highlight(&self, line: u32, column: u32) -> Option<String> {
+        let text = self.lines.get((line as usize).checked_sub(1)?)?;
+        let padding: String = text
+            .chars()
+            .take(column.saturating_sub(1) as usize)
+            .map(|c| if c == '\t' { '\t' } else { ' ' })
+            .collect();
+        Some(format!("    {}\n    {}^", text, padding))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Diagnostics, Phase};
+
+    #[test]
+    fn highlight_puts_caret_under_column() {
+        let diagnostics = Diagnostics::new(String::from("<test>"), "var a = 1;\nprint a +;\n");
+        assert_eq!(
+            diagnostics.highlight(2, 10),
+            Some(String::from("    print a +;\n             ^"))
+        );
+    }
+
+    #[test]
+    fn highlight_keeps_tabs_before_caret() {
+        let diagnostics = Diagnostics::new(String::from("<test>"), "\tx @");
+        assert_eq!(
+            diagnostics.highlight(1, 4),
+            Some(String::from("    \tx @\n    \t  ^"))
+        );
+    }
+
+    #[test]
+    fn highlight_needs_a_line_in_the_source() {
+        let diagnostics = Diagnostics::new(String::from("<test>"), "one line");
+        assert_eq!(diagnostics.highlight(0, 1), None);
+        assert_eq!(diagnostics.highlight(2, 1), None);
+    }
+
+    #[test]
+    fn errors_are_sorted_by_position() {
+        let mut diagnostics = Diagnostics::new(String::from("<test>"), "");
+        diagnostics.error(Phase::Parse, 2, 1, String::from("second"));
+        diagnostics.error(Phase::Scan, 1, 5, String::from("first"));
+        let messages: Vec<&str> = diagnostics
+            .sorted()
+            .iter()
+            .map(|diagnostic| diagnostic.message.as_str())
+            .collect();
+        assert_eq!(messages, ["first", "second"]);
+    }
+}
@@ -1,43 +1,95 @@
-use std::collections::HashMap;
+use std::any::Any;
+use std::rc::Rc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::interpreter::RuntimeError;
+use crate::scanner::{self, Token};
+use crate::statement::Statement;
+
+/* Rust trait objects can't have generic methods, so accepting a visitor lives
+in its own trait with one implementation per visitor return type. Expression
+then bundles together every return type the visitors need. */
+pub trait Accept<T> {
+    fn accept(&self, visitor: &mut dyn Visitor<T>) -> T;
+}
 
-use crate::Lox;
+pub trait Expression:
+    Accept<Result<scanner::Literal, RuntimeError>> + Accept<usize> + Accept<String> + Accept<()>
+{
+    // Used by the parser to check which kind of expression it has.
+    fn as_any(&self) -> &dyn Any;
 
-mod scanner;
+    // Used by the parser to take apart an expression it's rewriting.
+    fn into_any(self: Box<Self>) -> Box<dyn Any>;
+}
 
-use scanner::Token;
+impl<E> Expression for E
+where
+    E: Accept<Result<scanner::Literal, RuntimeError>>
+        + Accept<usize>
+        + Accept<String>
+        + Accept<()>
+        + Any,
+{
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
 
-pub trait Expression {
-    fn accept<T: Visitor>(&self, visitor: &mut T) -> T;
+    fn into_any(self: Box<Self>) -> Box<dyn Any> {
+        self
+    }
 }
 
-trait Visitor {
-    fn visit_assign_expression(&mut self, expression: Assign) -> T;
-    fn visit_binary_expression(&mut self, expression: Binary) -> T;
-    fn visit_call_expression(&mut self, expression: Call) -> T;
-    fn visit_get_expression(&mut self, expression: Get) -> T;
-    fn visit_grouping_expression(&mut self, expression: Grouping) -> T;
-    fn visit_literal_expression(&mut self, expression: Literal) -> T;
-    fn visit_logical_expression(&mut self, expression: Logical) -> T;
-    fn visit_set_expression(&mut self, expression: Set) -> T;
-    fn visit_super_expression(&mut self, expression: Super) -> T;
-    fn visit_this_expression(&mut self, expression: This) -> T;
-    fn visit_unary_expression(&mut self, expression: Unary) -> T;
-    fn visit_variable_expression(&mut self, expression: Variable) -> T;
+/* Expressions that refer to a variable get an id that is unique for the whole run,
+even across separately parsed sources, so the resolver can record where each one
+is bound without being confused by a node from an earlier parse. */
+static NEXT_ID: AtomicUsize = AtomicUsize::new(0);
+
+fn next_id() -> usize {
+    NEXT_ID.fetch_add(1, Ordering::Relaxed)
+}
+
+pub trait Visitor<T> {
+    fn visit_assign_expression(&mut self, expression: &Assign) -> T;
+    fn visit_binary_expression(&mut self, expression: &Binary) -> T;
+    fn visit_call_expression(&mut self, expression: &Call) -> T;
+    fn visit_get_expression(&mut self, expression: &Get) -> T;
+    fn visit_grouping_expression(&mut self, expression: &Grouping) -> T;
+    fn visit_index_expression(&mut self, expression: &Index) -> T;
+    fn visit_interpolation_expression(&mut self, expression: &Interpolation) -> T;
+    fn visit_lambda_expression(&mut self, expression: &Lambda) -> T;
+    fn visit_list_expression(&mut self, expression: &List) -> T;
+    fn visit_literal_expression(&mut self, expression: &Literal) -> T;
+    fn visit_logical_expression(&mut self, expression: &Logical) -> T;
+    fn visit_map_expression(&mut self, expression: &Map) -> T;
+    fn visit_set_expression(&mut self, expression: &Set) -> T;
+    fn visit_set_index_expression(&mut self, expression: &SetIndex) -> T;
+    fn visit_super_expression(&mut self, expression: &Super) -> T;
+    fn visit_this_expression(&mut self, expression: &This) -> T;
+    fn visit_unary_expression(&mut self, expression: &Unary) -> T;
+    fn visit_variable_expression(&mut self, expression: &Variable) -> T;
 }
 
 // Assign.
 
 pub struct Assign {
-    name: Token,
-    value: Expression,
+    pub id: usize,
+    pub name: Token,
+    pub value: Box<dyn Expression>,
 }
 
-impl Expression for Assign {
-    fn new(name: Token, value: Expression) -> Self {
-        Assign { name, value }
+impl Assign {
+    pub fn new(name: Token, value: Box<dyn Expression>) -> Self {
+        Assign {
+            id: next_id(),
+            name,
+            value,
+        }
     }
+}
 
-    fn accept<T: Visitor>(&self, visitor: &mut T) -> T {
+impl<T> Accept<T> for Assign {
+    fn accept(&self, visitor: &mut dyn Visitor<T>) -> T {
         visitor.visit_assign_expression(self)
     }
 }
@@ -45,21 +97,23 @@ impl Expression for Assign {
 // Binary.
 
 pub struct Binary {
-    left: Expression,
-    operator: Token,
-    right: Expression,
+    pub left: Box<dyn Expression>,
+    pub operator: Token,
+    pub right: Box<dyn Expression>,
 }
 
-impl Expression for Binary {
-    fn new(left: Expression, operator: Token, right: Expression) -> Self {
+impl Binary {
+    pub fn new(left: Box<dyn Expression>, operator: Token, right: Box<dyn Expression>) -> Self {
         Binary {
             left,
             operator,
             right,
         }
     }
+}
 
-    fn accept<T: Visitor>(&self, visitor: &mut T) -> T {
+impl<T> Accept<T> for Binary {
+    fn accept(&self, visitor: &mut dyn Visitor<T>) -> T {
         visitor.visit_binary_expression(self)
     }
 }
@@ -67,21 +121,27 @@ impl Expression for Binary {
 // Call.
 
 pub struct Call {
-    callee: Expression,
-    paren: Token,
-    arguments: Vec<Expression>,
+    pub callee: Box<dyn Expression>,
+    pub paren: Token, // The closing parenthesis, used to report errors.
+    pub arguments: Vec<Box<dyn Expression>>,
 }
 
-impl Expression for Call {
-    fn new(callee: Expression, paren: Token, arguments: Vec<Expression>) -> Self {
+impl Call {
+    pub fn new(
+        callee: Box<dyn Expression>,
+        paren: Token,
+        arguments: Vec<Box<dyn Expression>>,
+    ) -> Self {
         Call {
             callee,
             paren,
             arguments,
         }
     }
+}
 
-    fn accept<T: Visitor>(&self, visitor: &mut T) -> T {
+impl<T> Accept<T> for Call {
+    fn accept(&self, visitor: &mut dyn Visitor<T>) -> T {
         visitor.visit_call_expression(self)
     }
 }
@@ -89,16 +149,18 @@ impl Expression for Call {
 // Get.
 
 pub struct Get {
-    object: Expression,
-    name: Token,
+    pub object: Box<dyn Expression>,
+    pub name: Token,
 }
 
-impl Expression for Get {
-    fn new(object: Expression, name: Token) -> Self {
+impl Get {
+    pub fn new(object: Box<dyn Expression>, name: Token) -> Self {
         Get { object, name }
     }
+}
 
-    fn accept<T: Visitor>(&self, visitor: &mut T) -> T {
+impl<T> Accept<T> for Get {
+    fn accept(&self, visitor: &mut dyn Visitor<T>) -> T {
         visitor.visit_get_expression(self)
     }
 }
@@ -106,92 +168,250 @@ impl Expression for Get {
 // Grouping.
 
 pub struct Grouping {
-    expression: Expression,
+    pub expression: Box<dyn Expression>,
 }
 
-impl Expression for Grouping {
-    fn new(expression: Expression) -> Self {
+impl Grouping {
+    pub fn new(expression: Box<dyn Expression>) -> Self {
         Grouping { expression }
     }
+}
 
-    fn accept<T: Visitor>(&self, visitor: &mut T) -> T {
+impl<T> Accept<T> for Grouping {
+    fn accept(&self, visitor: &mut dyn Visitor<T>) -> T {
         visitor.visit_grouping_expression(self)
     }
 }
 
+// Index.
+
+pub struct Index {
+    pub object: Box<dyn Expression>,
+    pub bracket: Token,
+    pub index: Box<dyn Expression>,
+}
+
+impl Index {
+    pub fn new(object: Box<dyn Expression>, bracket: Token, index: Box<dyn Expression>) -> Self {
+        Index {
+            object,
+            bracket,
+            index,
+        }
+    }
+}
+
+impl<T> Accept<T> for Index {
+    fn accept(&self, visitor: &mut dyn Visitor<T>) -> T {
+        visitor.visit_index_expression(self)
+    }
+}
+
+// Interpolation.
+
+pub struct Interpolation {
+    pub parts: Vec<Box<dyn Expression>>, // Alternating string literals and interpolated expressions.
+}
+
+impl Interpolation {
+    pub fn new(parts: Vec<Box<dyn Expression>>) -> Self {
+        Interpolation { parts }
+    }
+}
+
+impl<T> Accept<T> for Interpolation {
+    fn accept(&self, visitor: &mut dyn Visitor<T>) -> T {
+        visitor.visit_interpolation_expression(self)
+    }
+}
+
+// Lambda.
+
+pub struct Lambda {
+    pub params: Vec<Token>,
+    pub body: Rc<Vec<Box<dyn Statement>>>, // Shared with the functions created from it.
+}
+
+impl Lambda {
+    pub fn new(params: Vec<Token>, body: Vec<Box<dyn Statement>>) -> Self {
+        Lambda {
+            params,
+            body: Rc::new(body),
+        }
+    }
+}
+
+impl<T> Accept<T> for Lambda {
+    fn accept(&self, visitor: &mut dyn Visitor<T>) -> T {
+        visitor.visit_lambda_expression(self)
+    }
+}
+
+// List.
+
+pub struct List {
+    pub elements: Vec<Box<dyn Expression>>,
+}
+
+impl List {
+    pub fn new(elements: Vec<Box<dyn Expression>>) -> Self {
+        List { elements }
+    }
+}
+
+impl<T> Accept<T> for List {
+    fn accept(&self, visitor: &mut dyn Visitor<T>) -> T {
+        visitor.visit_list_expression(self)
+    }
+}
+
 // Literal.
 
-pub struct Literal<G> {
-    expression: G,
+pub struct Literal {
+    pub value: scanner::Literal,
 }
 
-impl Expression for Literal<G> {
-    fn new(value: G) -> Self {
+impl Literal {
+    pub fn new(value: scanner::Literal) -> Self {
         Literal { value }
     }
+}
 
-    fn accept<T: Visitor>(&self, visitor: &mut T) -> T {
+impl<T> Accept<T> for Literal {
+    fn accept(&self, visitor: &mut dyn Visitor<T>) -> T {
         visitor.visit_literal_expression(self)
     }
 }
 
 // Logical.
 
+// An 'and' or 'or', kept apart from Binary since the right side isn't always evaluated.
 pub struct Logical {
-    left: Expression,
-    operator: Token,
-    right: Expression,
+    pub left: Box<dyn Expression>,
+    pub operator: Token,
+    pub right: Box<dyn Expression>,
 }
 
-impl Expression for Logical {
-    fn new(left: Expression, operator: Token, right: Expression) -> Self {
+impl Logical {
+    pub fn new(left: Box<dyn Expression>, operator: Token, right: Box<dyn Expression>) -> Self {
         Logical {
             left,
             operator,
             right,
         }
     }
+}
 
-    fn accept<T: Visitor>(&self, visitor: &mut T) -> T {
+impl<T> Accept<T> for Logical {
+    fn accept(&self, visitor: &mut dyn Visitor<T>) -> T {
         visitor.visit_logical_expression(self)
     }
 }
 
+// Map.
+
+pub struct Map {
+    pub brace: Token, // Where to report a key that can't be used.
+    pub entries: Vec<(Box<dyn Expression>, Box<dyn Expression>)>,
+}
+
+impl Map {
+    pub fn new(brace: Token, entries: Vec<(Box<dyn Expression>, Box<dyn Expression>)>) -> Self {
+        Map { brace, entries }
+    }
+}
+
+impl<T> Accept<T> for Map {
+    fn accept(&self, visitor: &mut dyn Visitor<T>) -> T {
+        visitor.visit_map_expression(self)
+    }
+}
+
 // Set.
 
 pub struct Set {
-    object: Expression,
-    name: Token,
-    value: Expression,
+    pub object: Box<dyn Expression>,
+    pub name: Token,
+    pub operator: Option<Token>, // Combines the old value with the new one, as in +=.
+    pub value: Box<dyn Expression>,
 }
 
-impl Expression for Set {
-    fn new(object: Expression, name: Token, value: Expression) -> Self {
+impl Set {
+    pub fn new(
+        object: Box<dyn Expression>,
+        name: Token,
+        operator: Option<Token>,
+        value: Box<dyn Expression>,
+    ) -> Self {
         Set {
             object,
             name,
+            operator,
             value,
         }
     }
+}
 
-    fn accept<T: Visitor>(&self, visitor: &mut T) -> T {
+impl<T> Accept<T> for Set {
+    fn accept(&self, visitor: &mut dyn Visitor<T>) -> T {
         visitor.visit_set_expression(self)
     }
 }
 
+// SetIndex.
+
+pub struct SetIndex {
+    pub object: Box<dyn Expression>,
+    pub bracket: Token,
+    pub index: Box<dyn Expression>,
+    pub operator: Option<Token>, // Combines the old value with the new one, as in +=.
+    pub value: Box<dyn Expression>,
+}
+
+impl SetIndex {
+    pub fn new(
+        object: Box<dyn Expression>,
+        bracket: Token,
+        index: Box<dyn Expression>,
+        operator: Option<Token>,
+        value: Box<dyn Expression>,
+    ) -> Self {
+        SetIndex {
+            object,
+            bracket,
+            index,
+            operator,
+            value,
+        }
+    }
+}
+
+impl<T> Accept<T> for SetIndex {
+    fn accept(&self, visitor: &mut dyn Visitor<T>) -> T {
+        visitor.visit_set_index_expression(self)
+    }
+}
+
 // Super.
 
 pub struct Super {
-    keyword: Token,
-    method: Token,
+    pub id: usize,
+    pub keyword: Token,
+    pub method: Token,
 }
 
-impl Expression for Super {
-    fn new(keyword: Token, method: Token) -> Self {
-        Super { keyword, method }
+impl Super {
+    pub fn new(keyword: Token, method: Token) -> Self {
+        Super {
+            id: next_id(),
+            keyword,
+            method,
+        }
     }
+}
 
-    fn accept<T: Visitor>(&self, visitor: &mut T) -> T {
+impl<T> Accept<T> for Super {
+    fn accept(&self, visitor: &mut dyn Visitor<T>) -> T {
         visitor.visit_super_expression(self)
     }
 }
@@ -199,15 +419,21 @@ impl Expression for Super {
 // This.
 
 pub struct This {
-    keyword: Token,
+    pub id: usize,
+    pub keyword: Token,
 }
 
-impl Expression for This {
-    fn new(keyword: Token) -> Self {
-        This { keyword }
+impl This {
+    pub fn new(keyword: Token) -> Self {
+        This {
+            id: next_id(),
+            keyword,
+        }
     }
+}
 
-    fn accept<T: Visitor>(&self, visitor: &mut T) -> T {
+impl<T> Accept<T> for This {
+    fn accept(&self, visitor: &mut dyn Visitor<T>) -> T {
         visitor.visit_this_expression(self)
     }
 }
@@ -215,16 +441,18 @@ impl Expression for This {
 // Unary.
 
 pub struct Unary {
-    operator: Token,
-    right: Expression,
+    pub operator: Token,
+    pub right: Box<dyn Expression>,
 }
 
-impl Expression for Unary {
-    fn new(operator: Token, right: Expression) -> Self {
+impl Unary {
+    pub fn new(operator: Token, right: Box<dyn Expression>) -> Self {
         Unary { operator, right }
     }
+}
 
-    fn accept<T: Visitor>(&self, visitor: &mut T) -> T {
+impl<T> Accept<T> for Unary {
+    fn accept(&self, visitor: &mut dyn Visitor<T>) -> T {
         visitor.visit_unary_expression(self)
     }
 }
@@ -232,15 +460,21 @@ impl Expression for Unary {
 // Variable.
 
 pub struct Variable {
-    name: Token,
+    pub id: usize,
+    pub name: Token,
 }
 
-impl Expression for Variable {
-    fn new(name: Token) -> Self {
-        Variable { name }
+impl Variable {
+    pub fn new(name: Token) -> Self {
+        Variable {
+            id: next_id(),
+            name,
+        }
     }
+}
 
-    fn accept<T: Visitor>(&self, visitor: &mut T) -> T {
+impl<T> Accept<T> for Variable {
+    fn accept(&self, visitor: &mut dyn Visitor<T>) -> T {
         visitor.visit_variable_expression(self)
     }
 }
@@ -0,0 +1,1631 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::f64::consts;
+use std::io::{self, BufRead, Write};
+use std::rc::Rc;
+
+mod callable;
+mod environment;
+mod lox_class;
+mod lox_function;
+mod lox_instance;
+mod lox_map;
+mod native_function;
+mod natives;
+mod runtime_error;
+mod unwind;
+
+use crate::expression::{
+    self, Assign, Binary, Call, Expression, Get, Grouping, Index, Interpolation, Lambda, List,
+    Logical, Map, Set, SetIndex, Super, This, Unary, Variable,
+};
+use crate::scanner::{Literal, LiteralKey, NilStyle, Token, TokenType};
+use crate::statement::{
+    self, Block, Break, Class, Continue, DoWhile, Function, If, Print, Record, Return, Statement,
+    Switch, Var, While,
+};
+
+pub use callable::Callable;
+use environment::Environment;
+pub use lox_class::LoxClass;
+pub use lox_function::LoxFunction;
+pub use lox_instance::LoxInstance;
+pub use lox_map::LoxMap;
+pub use native_function::{Native, NativeFunction};
+pub use runtime_error::RuntimeError;
+pub use unwind::Unwind;
+
+/* How deep calls can nest by default before a runtime error, rather than overflowing
+the Rust stack. Leaves room for bodies that nest a few loops and blocks within the
+STACK_SIZE in main.rs, even in a debug build. */
+const MAX_CALL_DEPTH: usize = 4_000;
+
+// The longest string, in bytes, that repeating one can make, rather than running out of memory.
+const MAX_REPEAT_LENGTH: usize = 1 << 28;
+
+pub struct Interpreter {
+    globals: Rc<RefCell<Environment>>,
+    environment: Rc<RefCell<Environment>>, // The innermost scope being executed.
+    locals: HashMap<usize, usize>,         // How many scopes out each local variable use is bound.
+    input: Option<Box<dyn BufRead>>,       // Where readLine() reads from, if not stdin.
+    output: Box<dyn Write>,                // Where print statements write to.
+    builtins: HashMap<String, Literal>,    // The globals every interpreter starts with.
+    call_depth: usize,                     // How many calls are currently in progress.
+    max_call_depth: usize,                 // How many calls can be in progress at once.
+    function: Option<Rc<LoxFunction>>,     // The function whose body is being executed.
+    nil_style: NilStyle,                   // How nil is spelled when a value is shown.
+    repl: bool,                            // Whether top-level expression statements are echoed.
+}
+
+impl Interpreter {
+    pub fn new() -> Self {
+        Interpreter::with_output(Box::new(io::stdout()))
+    }
+
+    // An interpreter whose scripts print to the given writer instead of stdout.
+    pub fn with_output(output: Box<dyn Write>) -> Self {
+        let mut globals = Environment::new();
+        natives::define(&mut globals);
+        // Plain globals rather than keywords, so scripts are free to redefine them.
+        globals.define(String::from("PI"), Literal::Number(consts::PI));
+        globals.define(String::from("E"), Literal::Number(consts::E));
+        let builtins = globals.values().clone();
+        let globals = Rc::new(RefCell::new(globals));
+
+        Interpreter {
+            environment: Rc::clone(&globals),
+            globals,
+            locals: HashMap::new(),
+            input: None,
+            output,
+            builtins,
+            call_depth: 0,
+            max_call_depth: MAX_CALL_DEPTH,
+            function: None,
+            nil_style: NilStyle::Nil,
+            repl: false,
+        }
+    }
+
+    // An interpreter whose scripts read their input from the given reader instead of stdin.
+    #[cfg(test)]
+    pub fn with_input(input: Box<dyn BufRead>) -> Self {
+        let mut interpreter = Interpreter::new();
+        interpreter.input = Some(input);
+        interpreter
+    }
+
+    /* The globals defined by the programs run so far, sorted by name. The built-in
+    ones are left out unless a program has replaced them. */
+    pub fn defined_globals(&self) -> Vec<(String, Literal)> {
+        let globals = self.globals.borrow();
+        let mut defined: Vec<(String, Literal)> = globals
+            .values()
+            .iter()
+            .filter(|(name, value)| self.builtins.get(*name) != Some(value))
+            .map(|(name, value)| (name.clone(), value.clone()))
+            .collect();
+        defined.sort_by(|left, right| left.0.cmp(&right.0));
+        defined
+    }
+
+    pub fn set_nil_style(&mut self, nil_style: NilStyle) {
+        self.nil_style = nil_style;
+    }
+
+    pub fn set_max_call_depth(&mut self, max_call_depth: usize) {
+        self.max_call_depth = max_call_depth;
+    }
+
+    /* At the prompt, a top-level expression statement prints its value, nil included,
+    so what you see is always what the expression evaluated to. */
+    pub fn set_repl(&mut self, repl: bool) {
+        self.repl = repl;
+    }
+
+    // Take on the resolver's depths for each use of a local variable, by expression id.
+    pub fn resolve(&mut self, locals: &HashMap<usize, usize>) {
+        self.locals.extend(locals);
+    }
+
+    // Execute each statement, stopping at the first runtime error and handing it back.
+    pub fn interpret(&mut self, statements: &[Box<dyn Statement>]) -> Result<(), RuntimeError> {
+        for statement in statements {
+            match self.execute(statement.as_ref()) {
+                Ok(()) => {}
+                Err(Unwind::Error(error)) => return Err(error),
+                // A return outside of a function ends the program.
+                Err(Unwind::Return(_)) => return Ok(()),
+                Err(Unwind::TailCall(_)) => {
+                    unreachable!("Only a function can call itself.")
+                }
+                Err(Unwind::Break) | Err(Unwind::Continue) => {
+                    unreachable!("The parser only allows 'break' and 'continue' inside a loop.")
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn execute(&mut self, statement: &dyn Statement) -> Result<(), Unwind> {
+        statement.accept(self)
+    }
+
+    /* Execute the statements in the given scope. The previous scope is restored
+    afterwards, even if one of the statements fails. */
+    fn execute_block(
+        &mut self,
+        statements: &[Box<dyn Statement>],
+        environment: Rc<RefCell<Environment>>,
+    ) -> Result<(), Unwind> {
+        let previous = Rc::clone(&self.environment);
+        self.environment = environment;
+
+        let mut result = Ok(());
+        for statement in statements {
+            result = self.execute(statement.as_ref());
+            if result.is_err() {
+                break;
+            }
+        }
+
+        self.environment = previous;
+        result
+    }
+
+    fn evaluate(&mut self, expression: &dyn Expression) -> Result<Literal, RuntimeError> {
+        expression.accept(self)
+    }
+
+    // False and nil are falsey, everything else is truthy.
+    fn is_truthy(&self, value: &Literal) -> bool {
+        match value {
+            Literal::Nil => false,
+            Literal::Bool(value) => *value,
+            _ => true,
+        }
+    }
+
+    /* Values of different types are never equal. Lists and maps are compared by
+    their elements and records by their fields, but a value is always equal to
+    itself, which is checked first so comparing a large list to itself is quick. */
+    fn is_equal(&self, left: &Literal, right: &Literal) -> bool {
+        self.is_equal_comparing(left, right, &mut Vec::new())
+    }
+
+    /* Compare the values, given the pairs of lists and maps already being compared
+    further out. A list that contains itself would otherwise be compared forever, so
+    a pair met again is taken to be equal and the rest of the elements decide. */
+    fn is_equal_comparing(
+        &self,
+        left: &Literal,
+        right: &Literal,
+        comparing: &mut Vec<(usize, usize)>,
+    ) -> bool {
+        match (left, right) {
+            (Literal::List(left), Literal::List(right)) => {
+                let pair = (Rc::as_ptr(left) as usize, Rc::as_ptr(right) as usize);
+                if Rc::ptr_eq(left, right) || comparing.contains(&pair) {
+                    return true;
+                }
+                let (left, right) = (left.borrow(), right.borrow());
+                comparing.push(pair);
+                let equal = left.len() == right.len()
+                    && left
+                        .iter()
+                        .zip(right.iter())
+                        .all(|(left, right)| self.is_equal_comparing(left, right, comparing));
+                comparing.pop();
+                equal
+            }
+            (Literal::Map(left), Literal::Map(right)) => {
+                let pair = (Rc::as_ptr(left) as usize, Rc::as_ptr(right) as usize);
+                if Rc::ptr_eq(left, right) || comparing.contains(&pair) {
+                    return true;
+                }
+                let (left, right) = (left.borrow(), right.borrow());
+                comparing.push(pair);
+                let equal = left.entries().len() == right.entries().len()
+                    && left
+                        .entries()
+                        .iter()
+                        .all(|(key, left)| match right.get(key) {
+                            Some(right) => self.is_equal_comparing(left, right, comparing),
+                            None => false,
+                        });
+                comparing.pop();
+                equal
+            }
+            (Literal::Instance(left), Literal::Instance(right)) => {
+                Rc::ptr_eq(left, right)
+                    || self.is_record_equal(&left.borrow(), &right.borrow(), comparing)
+            }
+            _ => left == right,
+        }
+    }
+
+    // Records of the same class are equal when all of their fields are.
+    fn is_record_equal(
+        &self,
+        left: &LoxInstance,
+        right: &LoxInstance,
+        comparing: &mut Vec<(usize, usize)>,
+    ) -> bool {
+        if !Rc::ptr_eq(left.class(), right.class()) {
+            return false;
+        }
+
+        match (left.record_values(), right.record_values()) {
+            (Some(left), Some(right)) => left
+                .iter()
+                .zip(&right)
+                .all(|(left, right)| self.is_equal_comparing(left, right, comparing)),
+            _ => false,
+        }
+    }
+
+    // Variables the resolver didn't find in any local scope are globals.
+    fn look_up_variable(&self, name: &Token, id: usize) -> Result<Literal, RuntimeError> {
+        match self.locals.get(&id) {
+            Some(distance) => self.environment.borrow().get_at(*distance, name),
+            None => self.globals.borrow().get(name),
+        }
+    }
+
+    // Convert the value to the text shown to the user.
+    fn stringify(&self, value: Literal) -> String {
+        value.display(self.nil_style).to_string()
+    }
+
+    // Evaluate the arguments of a call from left to right.
+    fn arguments(&mut self, call: &Call) -> Result<Vec<Literal>, RuntimeError> {
+        let mut arguments: Vec<Literal> = Vec::new();
+        for argument in &call.arguments {
+            arguments.push(self.evaluate(argument.as_ref())?);
+        }
+        Ok(arguments)
+    }
+
+    // Call the value with arguments that have already been evaluated.
+    fn call(
+        &mut self,
+        callee: Literal,
+        paren: &Token,
+        arguments: Vec<Literal>,
+    ) -> Result<Literal, RuntimeError> {
+        let function: Rc<dyn Callable> = match callee {
+            Literal::Function(function) => function,
+            Literal::Native(native) => native,
+            Literal::Class(class) => class,
+            _ => {
+                return Err(RuntimeError::new(
+                    paren.clone(),
+                    String::from("Can only call functions and classes."),
+                ))
+            }
+        };
+
+        let arity = function.arity();
+        let optional = function.optional();
+        if arguments.len() < arity || arguments.len() > arity + optional {
+            let expected = if optional == 0 {
+                arity.to_string()
+            } else {
+                format!("{} to {}", arity, arity + optional)
+            };
+            return Err(RuntimeError::new(
+                paren.clone(),
+                format!(
+                    "Expected {} arguments but got {}.",
+                    expected,
+                    arguments.len()
+                ),
+            ));
+        }
+
+        if self.call_depth >= self.max_call_depth {
+            return Err(RuntimeError::new(
+                paren.clone(),
+                String::from("Stack overflow."),
+            ));
+        }
+
+        self.call_depth += 1;
+        let result = function.call(self, paren, arguments);
+        self.call_depth -= 1;
+        result
+    }
+
+    /* Return the result of a call. When a function returns a call to itself, the
+    current call is reused by unwinding to it with the new arguments rather than
+    calling again, so tail recursion doesn't grow the stack however deep it goes. */
+    fn tail_call(&mut self, call: &Call) -> Result<(), Unwind> {
+        let callee = self.evaluate(call.callee.as_ref())?;
+        let arguments = self.arguments(call)?;
+
+        if let (Literal::Function(callee), Some(function)) = (&callee, &self.function) {
+            if Rc::ptr_eq(callee, function) && arguments.len() == callee.arity() {
+                return Err(Unwind::TailCall(arguments));
+            }
+        }
+
+        let value = self.call(callee, &call.paren, arguments)?;
+        Err(Unwind::Return(value))
+    }
+
+    // Create a function that closes over the current scope.
+    fn function(&self, declaration: &Function, is_initializer: bool) -> LoxFunction {
+        LoxFunction::new(
+            Some(declaration.name.clone()),
+            declaration.params.clone(),
+            declaration.guard.clone(),
+            Rc::clone(&declaration.body),
+            Rc::clone(&self.environment),
+            is_initializer,
+        )
+    }
+
+    // Apply a binary operator to operands that have already been evaluated.
+    fn binary(
+        &self,
+        operator: &Token,
+        left: Literal,
+        right: Literal,
+    ) -> Result<Literal, RuntimeError> {
+        match operator.token_type {
+            TokenType::BANG_EQUAL => Ok(Literal::Bool(!self.is_equal(&left, &right))),
+            TokenType::EQUAL_EQUAL => Ok(Literal::Bool(self.is_equal(&left, &right))),
+            TokenType::GREATER => {
+                let (left, right) = self.number_operands(operator, &left, &right)?;
+                Ok(Literal::Bool(left > right))
+            }
+            TokenType::GREATER_EQUAL => {
+                let (left, right) = self.number_operands(operator, &left, &right)?;
+                Ok(Literal::Bool(left >= right))
+            }
+            TokenType::LESS => {
+                let (left, right) = self.number_operands(operator, &left, &right)?;
+                Ok(Literal::Bool(left < right))
+            }
+            TokenType::LESS_EQUAL => {
+                let (left, right) = self.number_operands(operator, &left, &right)?;
+                Ok(Literal::Bool(left <= right))
+            }
+            TokenType::MINUS => {
+                let (left, right) = self.number_operands(operator, &left, &right)?;
+                Ok(Literal::Number(left - right))
+            }
+            /* The remainder takes the sign of the left operand, as Rust's % does,
+            so -7 % 3 is -1 rather than 2. */
+            TokenType::PERCENT => {
+                let (left, right) = self.number_operands(operator, &left, &right)?;
+                if right == 0.0 {
+                    return Err(RuntimeError::new(
+                        operator.clone(),
+                        String::from("Modulo by zero."),
+                    ));
+                }
+                Ok(Literal::Number(left % right))
+            }
+            TokenType::PLUS => match (left, right) {
+                (Literal::Number(left), Literal::Number(right)) => {
+                    Ok(Literal::Number(left + right))
+                }
+                (Literal::String(left), Literal::String(right)) => {
+                    Ok(Literal::String(left + &right))
+                }
+                // A number added to a string is formatted the same way print shows it.
+                (Literal::String(left), right @ Literal::Number(_)) => {
+                    Ok(Literal::String(left + &self.stringify(right)))
+                }
+                (left @ Literal::Number(_), Literal::String(right)) => {
+                    Ok(Literal::String(self.stringify(left) + &right))
+                }
+                _ => Err(RuntimeError::new(
+                    operator.clone(),
+                    String::from("Operands must be numbers or strings."),
+                )),
+            },
+            TokenType::SLASH => {
+                let (left, right) = self.number_operands(operator, &left, &right)?;
+                if right == 0.0 {
+                    return Err(RuntimeError::new(
+                        operator.clone(),
+                        String::from("Division by zero."),
+                    ));
+                }
+                Ok(Literal::Number(left / right))
+            }
+            TokenType::STAR_STAR => {
+                let (left, right) = self.number_operands(operator, &left, &right)?;
+                Ok(Literal::Number(left.powf(right)))
+            }
+            TokenType::STAR => match (left, right) {
+                (Literal::Number(left), Literal::Number(right)) => {
+                    Ok(Literal::Number(left * right))
+                }
+                (Literal::String(text), Literal::Number(count))
+                | (Literal::Number(count), Literal::String(text)) => {
+                    self.repeat(operator, &text, count)
+                }
+                _ => Err(RuntimeError::new(
+                    operator.clone(),
+                    String::from("Operands must be two numbers or a string and a number."),
+                )),
+            },
+            _ => unreachable!("Invalid binary operator."),
+        }
+    }
+
+    // Both operands must be numbers for arithmetic and comparison operators.
+    fn number_operands(
+        &self,
+        operator: &Token,
+        left: &Literal,
+        right: &Literal,
+    ) -> Result<(f64, f64), RuntimeError> {
+        match (left, right) {
+            (Literal::Number(left), Literal::Number(right)) => Ok((*left, *right)),
+            _ => Err(RuntimeError::new(
+                operator.clone(),
+                String::from("Operands must be numbers."),
+            )),
+        }
+    }
+
+    // The error for indexing anything other than a list or a map.
+    fn not_indexable(&self, bracket: &Token) -> RuntimeError {
+        RuntimeError::new(
+            bracket.clone(),
+            String::from("Only lists and maps can be indexed."),
+        )
+    }
+
+    // Turn a value into a map key, which only plain values can be.
+    fn map_key(&self, token: &Token, key: Literal) -> Result<LiteralKey, RuntimeError> {
+        LiteralKey::new(key).map_err(|message| RuntimeError::new(token.clone(), message))
+    }
+
+    /* Turn the index into a position in a list of the given length. Negative
+    indices count back from the end, so -1 is the last element. */
+    fn list_index(
+        &self,
+        bracket: &Token,
+        length: usize,
+        index: &Literal,
+    ) -> Result<usize, RuntimeError> {
+        let index = match index {
+            Literal::Number(index) if index.fract() == 0.0 => *index,
+            _ => {
+                return Err(RuntimeError::new(
+                    bracket.clone(),
+                    String::from("List index must be an integer."),
+                ))
+            }
+        };
+
+        let position = if index < 0.0 {
+            index + length as f64
+        } else {
+            index
+        };
+        if position < 0.0 || position >= length as f64 {
+            return Err(RuntimeError::new(
+                bracket.clone(),
+                format!("List index {} is out of range.", index),
+            ));
+        }
+        Ok(position as usize)
+    }
+
+    // Multiplying a string by a whole number repeats it that many times.
+    fn repeat(&self, operator: &Token, text: &str, count: f64) -> Result<Literal, RuntimeError> {
+        if count < 0.0 || count.fract() != 0.0 {
+            return Err(RuntimeError::new(
+                operator.clone(),
+                String::from("Repeat count must be a non-negative integer."),
+            ));
+        }
+
+        // A count too large for usize becomes usize::MAX, which is always too long.
+        let count = count as usize;
+        match text.len().checked_mul(count) {
+            Some(length) if length <= MAX_REPEAT_LENGTH => Ok(Literal::String(text.repeat(count))),
+            _ => Err(RuntimeError::new(
+                operator.clone(),
+                String::from("Repeated string is too long."),
+            )),
+        }
+    }
+}
+
+impl expression::Visitor<Result<Literal, RuntimeError>> for Interpreter {
+    fn visit_assign_expression(&mut self, expression: &Assign) -> Result<Literal, RuntimeError> {
+        let value = self.evaluate(expression.value.as_ref())?;
+        match self.locals.get(&expression.id) {
+            Some(distance) => self.environment.borrow_mut().assign_at(
+                *distance,
+                &expression.name,
+                value.clone(),
+            )?,
+            None => self
+                .globals
+                .borrow_mut()
+                .assign(&expression.name, value.clone())?,
+        }
+
+        // Assignment is an expression, so it evaluates to the assigned value.
+        Ok(value)
+    }
+
+    fn visit_binary_expression(&mut self, expression: &Binary) -> Result<Literal, RuntimeError> {
+        let left = self.evaluate(expression.left.as_ref())?;
+        let right = self.evaluate(expression.right.as_ref())?;
+        self.binary(&expression.operator, left, right)
+    }
+
+    fn visit_call_expression(&mut self, expression: &Call) -> Result<Literal, RuntimeError> {
+        let callee = self.evaluate(expression.callee.as_ref())?;
+        let arguments = self.arguments(expression)?;
+        self.call(callee, &expression.paren, arguments)
+    }
+
+    fn visit_get_expression(&mut self, expression: &Get) -> Result<Literal, RuntimeError> {
+        match self.evaluate(expression.object.as_ref())? {
+            Literal::Instance(instance) => LoxInstance::get(&instance, &expression.name),
+            _ => Err(RuntimeError::new(
+                expression.name.clone(),
+                String::from("Only instances have properties."),
+            )),
+        }
+    }
+
+    fn visit_grouping_expression(
+        &mut self,
+        expression: &Grouping,
+    ) -> Result<Literal, RuntimeError> {
+        self.evaluate(expression.expression.as_ref())
+    }
+
+    fn visit_index_expression(&mut self, expression: &Index) -> Result<Literal, RuntimeError> {
+        let object = self.evaluate(expression.object.as_ref())?;
+        let index = self.evaluate(expression.index.as_ref())?;
+        match object {
+            Literal::List(list) => {
+                let list = list.borrow();
+                let index = self.list_index(&expression.bracket, list.len(), &index)?;
+                Ok(list[index].clone())
+            }
+            // A missing key is nil, so get() and has() are how to tell it from a key mapped to nil.
+            Literal::Map(map) => {
+                let key = self.map_key(&expression.bracket, index)?;
+                Ok(map.borrow().get(&key).cloned().unwrap_or(Literal::Nil))
+            }
+            _ => Err(self.not_indexable(&expression.bracket)),
+        }
+    }
+
+    // Join the string parts and the values of the expressions between them into one string.
+    fn visit_interpolation_expression(
+        &mut self,
+        expression: &Interpolation,
+    ) -> Result<Literal, RuntimeError> {
+        let mut value = String::new();
+        for part in &expression.parts {
+            let part = self.evaluate(part.as_ref())?;
+            value.push_str(&self.stringify(part));
+        }
+        Ok(Literal::String(value))
+    }
+
+    // A lambda closes over the scope it's evaluated in, just like a declaration.
+    fn visit_lambda_expression(&mut self, expression: &Lambda) -> Result<Literal, RuntimeError> {
+        Ok(Literal::Function(Rc::new(LoxFunction::new(
+            None,
+            expression.params.clone(),
+            None,
+            Rc::clone(&expression.body),
+            Rc::clone(&self.environment),
+            false,
+        ))))
+    }
+
+    fn visit_list_expression(&mut self, expression: &List) -> Result<Literal, RuntimeError> {
+        let mut elements = Vec::new();
+        for element in &expression.elements {
+            elements.push(self.evaluate(element.as_ref())?);
+        }
+        Ok(Literal::List(Rc::new(RefCell::new(elements))))
+    }
+
+    fn visit_literal_expression(
+        &mut self,
+        expression: &expression::Literal,
+    ) -> Result<Literal, RuntimeError> {
+        Ok(expression.value.clone())
+    }
+
+    // The right side is only evaluated when the left doesn't already decide the result.
+    fn visit_logical_expression(&mut self, expression: &Logical) -> Result<Literal, RuntimeError> {
+        let left = self.evaluate(expression.left.as_ref())?;
+
+        let decided = match expression.operator.token_type {
+            TokenType::OR => self.is_truthy(&left),
+            _ => !self.is_truthy(&left),
+        };
+        if decided {
+            return Ok(left);
+        }
+
+        self.evaluate(expression.right.as_ref())
+    }
+
+    // A key given twice keeps its first place in the map but takes the later value.
+    fn visit_map_expression(&mut self, expression: &Map) -> Result<Literal, RuntimeError> {
+        let mut map = LoxMap::new();
+        for (key, value) in &expression.entries {
+            let key = self.evaluate(key.as_ref())?;
+            let key = self.map_key(&expression.brace, key)?;
+            map.insert(key, self.evaluate(value.as_ref())?);
+        }
+        Ok(Literal::Map(Rc::new(RefCell::new(map))))
+    }
+
+    fn visit_set_expression(&mut self, expression: &Set) -> Result<Literal, RuntimeError> {
+        let instance = match self.evaluate(expression.object.as_ref())? {
+            Literal::Instance(instance) => instance,
+            _ => {
+                return Err(RuntimeError::new(
+                    expression.name.clone(),
+                    String::from("Only instances have fields."),
+                ))
+            }
+        };
+
+        let mut value = self.evaluate(expression.value.as_ref())?;
+        if let Some(operator) = &expression.operator {
+            let current = LoxInstance::get(&instance, &expression.name)?;
+            value = self.binary(operator, current, value)?;
+        }
+        instance.borrow_mut().set(&expression.name, value.clone())?;
+        Ok(value)
+    }
+
+    fn visit_set_index_expression(
+        &mut self,
+        expression: &SetIndex,
+    ) -> Result<Literal, RuntimeError> {
+        let object = self.evaluate(expression.object.as_ref())?;
+        let index = self.evaluate(expression.index.as_ref())?;
+        let mut value = self.evaluate(expression.value.as_ref())?;
+        match object {
+            Literal::List(list) => {
+                let mut list = list.borrow_mut();
+                let index = self.list_index(&expression.bracket, list.len(), &index)?;
+                if let Some(operator) = &expression.operator {
+                    value = self.binary(operator, list[index].clone(), value)?;
+                }
+                list[index] = value.clone();
+            }
+            Literal::Map(map) => {
+                let key = self.map_key(&expression.bracket, index)?;
+                if let Some(operator) = &expression.operator {
+                    let current = map.borrow().get(&key).cloned().unwrap_or(Literal::Nil);
+                    value = self.binary(operator, current, value)?;
+                }
+                map.borrow_mut().insert(key, value.clone());
+            }
+            _ => return Err(self.not_indexable(&expression.bracket)),
+        }
+        Ok(value)
+    }
+
+    /* Look the method up starting at the superclass of the class containing the
+    method being run, but bind it to the current instance. */
+    fn visit_super_expression(&mut self, expression: &Super) -> Result<Literal, RuntimeError> {
+        // The scope binding 'this' is always just inside the one binding 'super'.
+        let distance = self.locals[&expression.id];
+        let superclass = match self
+            .environment
+            .borrow()
+            .get_at(distance, &expression.keyword)?
+        {
+            Literal::Class(class) => class,
+            _ => unreachable!("'super' is always bound to a class."),
+        };
+        let keyword = &expression.keyword;
+        let this = Token::new(
+            TokenType::THIS,
+            String::from("this"),
+            Literal::Nil,
+            keyword.line,
+            keyword.column,
+        );
+        let instance = match self.environment.borrow().get_at(distance - 1, &this)? {
+            Literal::Instance(instance) => instance,
+            _ => unreachable!("'this' is always bound to an instance."),
+        };
+
+        match superclass.find_method(&expression.method.lexeme) {
+            Some(method) => Ok(Literal::Function(Rc::new(method.bind(instance)))),
+            None => Err(RuntimeError::new(
+                expression.method.clone(),
+                format!("Undefined property '{}'.", expression.method.lexeme),
+            )),
+        }
+    }
+
+    // 'this' is defined in the environment of every bound method.
+    fn visit_this_expression(&mut self, expression: &This) -> Result<Literal, RuntimeError> {
+        self.look_up_variable(&expression.keyword, expression.id)
+    }
+
+    fn visit_unary_expression(&mut self, expression: &Unary) -> Result<Literal, RuntimeError> {
+        let right = self.evaluate(expression.right.as_ref())?;
+
+        match expression.operator.token_type {
+            TokenType::BANG => Ok(Literal::Bool(!self.is_truthy(&right))),
+            TokenType::MINUS => match right {
+                Literal::Number(value) => Ok(Literal::Number(-value)),
+                _ => Err(RuntimeError::new(
+                    expression.operator.clone(),
+                    String::from("Operand must be a number."),
+                )),
+            },
+            _ => unreachable!("Invalid unary operator."),
+        }
+    }
+
+    fn visit_variable_expression(
+        &mut self,
+        expression: &Variable,
+    ) -> Result<Literal, RuntimeError> {
+        self.look_up_variable(&expression.name, expression.id)
+    }
+}
+
+impl statement::Visitor<Result<(), Unwind>> for Interpreter {
+    fn visit_block_statement(&mut self, statement: &Block) -> Result<(), Unwind> {
+        let environment = Environment::with_enclosing(Rc::clone(&self.environment));
+        self.execute_block(&statement.statements, Rc::new(RefCell::new(environment)))
+    }
+
+    // Unwind up to the loop, which stops when it sees the break.
+    fn visit_break_statement(&mut self, _statement: &Break) -> Result<(), Unwind> {
+        Err(Unwind::Break)
+    }
+
+    fn visit_class_statement(&mut self, statement: &Class) -> Result<(), Unwind> {
+        let mut superclass = None;
+        if let Some(variable) = &statement.superclass {
+            // Records can't be extended, since their fields are fixed.
+            match self.evaluate(variable)? {
+                Literal::Class(class) if class.fields().is_none() => superclass = Some(class),
+                _ => {
+                    return Err(Unwind::Error(RuntimeError::new(
+                        variable.name.clone(),
+                        String::from("Superclass must be a class."),
+                    )))
+                }
+            }
+        }
+
+        // The methods close over a scope where 'super' is the superclass.
+        let enclosing = Rc::clone(&self.environment);
+        if let Some(superclass) = &superclass {
+            let mut environment = Environment::with_enclosing(Rc::clone(&enclosing));
+            environment.define(String::from("super"), Literal::Class(Rc::clone(superclass)));
+            self.environment = Rc::new(RefCell::new(environment));
+        }
+
+        let mut methods: HashMap<String, Rc<LoxFunction>> = HashMap::new();
+        for method in &statement.methods {
+            let function = self.function(method, method.name.lexeme == "init");
+            methods.insert(method.name.lexeme.clone(), Rc::new(function));
+        }
+
+        self.environment = enclosing;
+        let class = LoxClass::new(statement.name.lexeme.clone(), superclass, methods);
+
+        self.environment.borrow_mut().define(
+            statement.name.lexeme.clone(),
+            Literal::Class(Rc::new(class)),
+        );
+        Ok(())
+    }
+
+    // Unwind up to the loop, which moves on to its next iteration.
+    fn visit_continue_statement(&mut self, _statement: &Continue) -> Result<(), Unwind> {
+        Err(Unwind::Continue)
+    }
+
+    // Like a while loop, except the condition is first checked after the body has run once.
+    fn visit_do_while_statement(&mut self, statement: &DoWhile) -> Result<(), Unwind> {
+        loop {
+            match self.execute(statement.body.as_ref()) {
+                Err(Unwind::Break) => break,
+                Err(Unwind::Continue) => {}
+                result => result?,
+            }
+
+            let condition = self.evaluate(statement.condition.as_ref())?;
+            if !self.is_truthy(&condition) {
+                break;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn visit_expression_statement(
+        &mut self,
+        statement: &statement::Expression,
+    ) -> Result<(), Unwind> {
+        let value = self.evaluate(statement.expression.as_ref())?;
+        if self.repl && Rc::ptr_eq(&self.environment, &self.globals) {
+            let text = self.stringify(value);
+            writeln!(self.output, "{}", text).expect("Failed to write output.");
+        }
+        Ok(())
+    }
+
+    fn visit_function_statement(&mut self, statement: &Function) -> Result<(), Unwind> {
+        let function = self.function(statement, false);
+
+        self.environment.borrow_mut().define(
+            statement.name.lexeme.clone(),
+            Literal::Function(Rc::new(function)),
+        );
+        Ok(())
+    }
+
+    fn visit_if_statement(&mut self, statement: &If) -> Result<(), Unwind> {
+        let condition = self.evaluate(statement.condition.as_ref())?;
+
+        if self.is_truthy(&condition) {
+            self.execute(statement.then_branch.as_ref())?;
+        } else if let Some(else_branch) = &statement.else_branch {
+            self.execute(else_branch.as_ref())?;
+        }
+
+        Ok(())
+    }
+
+    fn visit_print_statement(&mut self, statement: &Print) -> Result<(), Unwind> {
+        let value = self.evaluate(statement.expression.as_ref())?;
+        let text = self.stringify(value);
+        writeln!(self.output, "{}", text).expect("Failed to write output.");
+        Ok(())
+    }
+
+    fn visit_record_statement(&mut self, statement: &Record) -> Result<(), Unwind> {
+        let fields = statement
+            .fields
+            .iter()
+            .map(|field| field.lexeme.clone())
+            .collect();
+        let class = LoxClass::record(statement.name.lexeme.clone(), fields);
+
+        self.environment.borrow_mut().define(
+            statement.name.lexeme.clone(),
+            Literal::Class(Rc::new(class)),
+        );
+        Ok(())
+    }
+
+    fn visit_return_statement(&mut self, statement: &Return) -> Result<(), Unwind> {
+        let value = match &statement.value {
+            Some(value) => match value.as_any().downcast_ref::<Call>() {
+                Some(call) => return self.tail_call(call),
+                None => self.evaluate(value.as_ref())?,
+            },
+            None => Literal::Nil,
+        };
+
+        // Unwind all the way back to the call that's being returned from.
+        Err(Unwind::Return(value))
+    }
+
+    // Run the body of the first case whose value equals the subject, or else the default.
+    fn visit_switch_statement(&mut self, statement: &Switch) -> Result<(), Unwind> {
+        let subject = self.evaluate(statement.subject.as_ref())?;
+
+        let mut body = statement.default.as_ref();
+        for case in &statement.cases {
+            let value = self.evaluate(case.value.as_ref())?;
+            if self.is_equal(&subject, &value) {
+                body = Some(&case.body);
+                break;
+            }
+        }
+
+        match body {
+            Some(body) => {
+                let environment = Environment::with_enclosing(Rc::clone(&self.environment));
+                self.execute_block(body, Rc::new(RefCell::new(environment)))
+            }
+            None => Ok(()),
+        }
+    }
+
+    fn visit_var_statement(&mut self, statement: &Var) -> Result<(), Unwind> {
+        // Variables without an initializer start out as nil.
+        let value = match &statement.initializer {
+            Some(initializer) => self.evaluate(initializer.as_ref())?,
+            None => Literal::Nil,
+        };
+
+        self.environment
+            .borrow_mut()
+            .define(statement.name.lexeme.clone(), value);
+        Ok(())
+    }
+
+    fn visit_while_statement(&mut self, statement: &While) -> Result<(), Unwind> {
+        // The condition is evaluated again before every iteration.
+        loop {
+            let condition = self.evaluate(statement.condition.as_ref())?;
+            if !self.is_truthy(&condition) {
+                break;
+            }
+
+            match self.execute(statement.body.as_ref()) {
+                Err(Unwind::Break) => break,
+                Err(Unwind::Continue) => {}
+                result => result?,
+            }
+
+            for increment in &statement.increments {
+                self.evaluate(increment.as_ref())?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Interpreter;
+    use crate::testing::{on_interpreter_stack, parse, run, run_error, Output, Session};
+
+    #[test]
+    fn print_statement() {
+        assert_eq!(run("print 1 + 2;"), "3.0\n");
+    }
+
+    #[test]
+    fn map_index_get_and_set() {
+        let source = r#"
+            var m = {"a": 1, 2: "two"};
+            m["a"] += 10;
+            m["c"] = nil;
+            print m;
+            print m[2];
+        "#;
+        assert_eq!(run(source), "{a: 11.0, 2.0: two, c: nil}\ntwo\n");
+    }
+
+    #[test]
+    fn map_key_must_be_plain_value() {
+        assert_eq!(
+            run_error("var m = {}; m[clock] = 1;"),
+            "Only numbers, strings, booleans and nil can be used as keys."
+        );
+    }
+
+    #[test]
+    fn assignment_updates_variable() {
+        assert_eq!(run("var a = 1; a = 2; print a;"), "2.0\n");
+    }
+
+    #[test]
+    fn assignment_to_undefined_variable() {
+        assert_eq!(run_error("b = 2;"), "Undefined variable 'b'.");
+    }
+
+    #[test]
+    fn bound_method_keeps_its_instance() {
+        let source = r#"
+            class Counter {
+                init(name) { this.name = name; }
+                describe() { return this.name; }
+            }
+            var first = Counter("first");
+            var method = first.describe;
+            var second = Counter("second");
+            print method();
+        "#;
+        assert_eq!(run(source), "first\n");
+    }
+
+    #[test]
+    fn blocks_shadow_outer_variables() {
+        let source = r#"
+            var a = "outer";
+            {
+                var a = "middle";
+                {
+                    var a = "inner";
+                    print a;
+                }
+                print a;
+            }
+            print a;
+        "#;
+        assert_eq!(run(source), "inner\nmiddle\nouter\n");
+    }
+
+    #[test]
+    fn assignment_in_block_updates_nearest_binding() {
+        assert_eq!(run("var a = 1; { a = 2; } print a;"), "2.0\n");
+    }
+
+    #[test]
+    fn if_takes_then_branch() {
+        assert_eq!(run("if (true) print 1; else print 2;"), "1.0\n");
+    }
+
+    #[test]
+    fn if_without_else_can_do_nothing() {
+        assert_eq!(run("if (nil) print 1;"), "");
+    }
+
+    #[test]
+    fn dangling_else_binds_to_nearest_if() {
+        assert_eq!(run("if (true) if (false) print 1; else print 2;"), "2.0\n");
+    }
+
+    #[test]
+    fn while_loop_counts_down() {
+        let source = "var i = 3; while (i > 0) { print i; i = i - 1; }";
+        assert_eq!(run(source), "3.0\n2.0\n1.0\n");
+    }
+
+    #[test]
+    fn for_loop_with_two_variables() {
+        let source = r#"
+            for (var i = 0, j = 10; i < j; i = i + 1, j = j - 1) {
+                print string(i) + " " + string(j);
+            }
+        "#;
+        assert_eq!(
+            run(source),
+            "0.0 10.0\n1.0 9.0\n2.0 8.0\n3.0 7.0\n4.0 6.0\n"
+        );
+    }
+
+    #[test]
+    fn for_loop_assigning_two_variables() {
+        let source = "var i; var j; for (i = 0, j = 3; i < j; i = i + 1) print i; print j;";
+        assert_eq!(run(source), "0.0\n1.0\n2.0\n3.0\n");
+    }
+
+    #[test]
+    fn for_loop_counts_up() {
+        assert_eq!(
+            run("for (var i = 0; i < 3; i = i + 1) print i;"),
+            "0.0\n1.0\n2.0\n"
+        );
+    }
+
+    #[test]
+    fn list_is_equal_to_itself_without_comparing_elements() {
+        // NaN isn't equal to itself, so only the identity check can make this true.
+        let source = r#"
+            var a = [pow(-1, 0.5)];
+            for (var i = 0; i < 10000; i = i + 1) push(a, i);
+            print a == a;
+            print a == [pow(-1, 0.5)];
+        "#;
+        assert_eq!(run(source), "true\nfalse\n");
+    }
+
+    #[test]
+    fn lists_and_maps_compare_by_elements() {
+        let source = r#"
+            print [1, [2, "x"]] == [1, [2, "x"]];
+            print [1, 2] == [1, 2, 3];
+            print {"a": 1, "b": nil} == {"b": nil, "a": 1};
+            print {"a": 1} == {"a": 2};
+            var a = []; push(a, a);
+            var b = []; push(b, b);
+            print a == b;
+        "#;
+        assert_eq!(run(source), "true\nfalse\ntrue\nfalse\ntrue\n");
+    }
+
+    #[test]
+    fn function_call_returns_value() {
+        assert_eq!(
+            run("fun add(a, b) { return a + b; } print add(1, 2);"),
+            "3.0\n"
+        );
+    }
+
+    #[test]
+    fn only_functions_and_classes_can_be_called() {
+        assert_eq!(
+            run_error("\"text\"();"),
+            "Can only call functions and classes."
+        );
+    }
+
+    #[test]
+    fn call_satisfying_guard() {
+        assert_eq!(
+            run("fun f(n) when n > 0 { return n; } print f(1);"),
+            "1.0\n"
+        );
+    }
+
+    #[test]
+    fn call_failing_guard() {
+        assert_eq!(
+            run_error("fun f(n) when n > 0 { return n; } f(0);"),
+            "Guard failed for f."
+        );
+    }
+
+    #[test]
+    fn callables_show_their_kind_name_and_arity() {
+        let source = r#"
+            fun add(a, b) {}
+            class Point {}
+            print add;
+            print fun (x) {};
+            print Point;
+            print clock;
+        "#;
+        assert_eq!(
+            run(source),
+            "<fn add/2>\n<fn/1>\n<class Point>\n<native clock>\n"
+        );
+    }
+
+    #[test]
+    fn closures_share_captured_variable() {
+        let source = r#"
+            fun makeCounter() {
+                var i = 0;
+                fun count() {
+                    i = i + 1;
+                    return i;
+                }
+                return count;
+            }
+            var counter = makeCounter();
+            print counter();
+            print counter();
+        "#;
+        assert_eq!(run(source), "1.0\n2.0\n");
+    }
+
+    #[test]
+    fn instance_fields_can_be_set_and_read() {
+        assert_eq!(
+            run("class Foo {} var foo = Foo(); foo.bar = 1; print foo.bar;"),
+            "1.0\n"
+        );
+    }
+
+    #[test]
+    fn reading_undefined_property() {
+        assert_eq!(
+            run_error("class Foo {} Foo().bar;"),
+            "Undefined property 'bar'."
+        );
+    }
+
+    #[test]
+    fn method_returns_field_through_this() {
+        let source = r#"
+            class Person {
+                describe() { return this.name; }
+            }
+            var person = Person();
+            person.name = "Ada";
+            print person.describe();
+        "#;
+        assert_eq!(run(source), "Ada\n");
+    }
+
+    #[test]
+    fn init_sets_fields_on_construction() {
+        let source = "class Foo { init(x) { this.x = x; } } print Foo(5).x;";
+        assert_eq!(run(source), "5.0\n");
+    }
+
+    #[test]
+    fn init_returns_instance_even_with_bare_return() {
+        let source = "class Foo { init() { return; } } print Foo().init();";
+        assert_eq!(run(source), "Foo instance\n");
+    }
+
+    #[test]
+    fn record_constructs_and_displays() {
+        assert_eq!(
+            run("record Point(x, y); var p = Point(1, 2); print p; print p.x;"),
+            "Point(1.0, 2.0)\n1.0\n"
+        );
+    }
+
+    #[test]
+    fn records_compare_by_fields() {
+        let source = "record Point(x, y); print Point(1, 2) == Point(1, 2); print Point(1, 2) == Point(2, 1);";
+        assert_eq!(run(source), "true\nfalse\n");
+    }
+
+    #[test]
+    fn record_fields_cannot_be_assigned() {
+        assert_eq!(
+            run_error("record Point(x, y); var p = Point(1, 2); p.x = 3;"),
+            "Can't assign to field 'x' of a record."
+        );
+    }
+
+    #[test]
+    fn subclass_combines_with_super_method() {
+        let source = r#"
+            class Greeter {
+                greet() { return "Hello"; }
+            }
+            class LoudGreeter < Greeter {
+                greet() { return super.greet() + "!"; }
+            }
+            print LoudGreeter().greet();
+        "#;
+        assert_eq!(run(source), "Hello!\n");
+    }
+
+    #[test]
+    fn superclass_must_be_a_class() {
+        assert_eq!(
+            run_error("var NotAClass = 1; class Sub < NotAClass {}"),
+            "Superclass must be a class."
+        );
+    }
+
+    #[test]
+    fn break_stops_loop() {
+        let source = "for (var i = 0; i < 10; i = i + 1) { if (i == 3) break; print i; }";
+        assert_eq!(run(source), "0.0\n1.0\n2.0\n");
+    }
+
+    #[test]
+    fn continue_still_runs_increment() {
+        let source = "for (var i = 0; i < 5; i = i + 1) { if (i == 2) continue; print i; }";
+        assert_eq!(run(source), "0.0\n1.0\n3.0\n4.0\n");
+    }
+
+    #[test]
+    fn lambda_called_immediately() {
+        assert_eq!(run("print (fun (x) { return x * x; })(4);"), "16.0\n");
+    }
+
+    #[test]
+    fn modulo_keeps_sign_of_dividend() {
+        assert_eq!(run("print 10 % 3 == 1; print -7 % 3;"), "true\n-1.0\n");
+    }
+
+    #[test]
+    fn modulo_by_zero() {
+        assert_eq!(run_error("print 7 % 0;"), "Modulo by zero.");
+    }
+
+    #[test]
+    fn division_by_zero() {
+        assert_eq!(run_error("print 1 / 0;"), "Division by zero.");
+    }
+
+    #[test]
+    fn division_by_small_number() {
+        assert_eq!(run("print 1 / 0.0000001;"), "10000000.0\n");
+    }
+
+    #[test]
+    fn string_repetition() {
+        assert_eq!(
+            run(r#"print "ab" * 3; print 3 * "x"; print "" * pow(10, 300);"#),
+            "ababab\nxxx\n\n"
+        );
+    }
+
+    #[test]
+    fn repeat_count_must_be_non_negative_integer() {
+        assert_eq!(
+            run_error(r#""a" * -1;"#),
+            "Repeat count must be a non-negative integer."
+        );
+    }
+
+    #[test]
+    fn repeated_string_too_long() {
+        assert_eq!(
+            run_error(r#""ab" * pow(10, 18);"#),
+            "Repeated string is too long."
+        );
+        assert_eq!(
+            run_error(r#""ab" * pow(10, 300);"#),
+            "Repeated string is too long."
+        );
+    }
+
+    #[test]
+    fn plus_stringifies_the_other_operand() {
+        assert_eq!(
+            run(r#"print "x" + 1; print 1 + "x"; print 1 + 2;"#),
+            "x1.0\n1.0x\n3.0\n"
+        );
+    }
+
+    #[test]
+    fn print_writes_to_injected_output() {
+        let output = Output::default();
+        let mut interpreter = Interpreter::with_output(Box::new(output.clone()));
+        let statements = parse("print 1; print 2;");
+        assert!(interpreter.interpret(&statements).is_ok());
+        assert_eq!(output.text(), "1.0\n2.0\n");
+    }
+
+    #[test]
+    fn list_literals_display_their_elements() {
+        assert_eq!(run("print [1, 2, 3];"), "[1.0, 2.0, 3.0]\n");
+        assert_eq!(run("print [];"), "[]\n");
+        assert_eq!(
+            run("print [[1, 2], [], [\"a\"]];"),
+            "[[1.0, 2.0], [], [a]]\n"
+        );
+    }
+
+    #[test]
+    fn lists_containing_themselves_display() {
+        assert_eq!(run("var a = []; push(a, a); print a;"), "[[...]]\n");
+        let source = "var a = [1]; var m = {\"a\": a}; push(a, m); print m;";
+        assert_eq!(run(source), "{a: [1.0, {...}]}\n");
+    }
+
+    #[test]
+    fn shared_list_is_not_a_cycle() {
+        assert_eq!(run("var a = [1]; print [a, a];"), "[[1.0], [1.0]]\n");
+    }
+
+    #[test]
+    fn list_index_get_and_set() {
+        let source = "var xs = [1, 2, 3]; xs[0] = 9; print xs[0]; print xs[-1]; print xs;";
+        assert_eq!(run(source), "9.0\n3.0\n[9.0, 2.0, 3.0]\n");
+    }
+
+    #[test]
+    fn list_index_out_of_range() {
+        assert_eq!(
+            run_error("print [1, 2][2];"),
+            "List index 2 is out of range."
+        );
+        assert_eq!(
+            run_error("print [1, 2][-3];"),
+            "List index -3 is out of range."
+        );
+        assert_eq!(
+            run_error("var xs = []; xs[0] = 1;"),
+            "List index 0 is out of range."
+        );
+    }
+
+    #[test]
+    fn list_index_must_be_an_integer() {
+        assert_eq!(
+            run_error("print [1, 2][0.5];"),
+            "List index must be an integer."
+        );
+        assert_eq!(
+            run_error("print [1, 2][\"0\"];"),
+            "List index must be an integer."
+        );
+    }
+
+    #[test]
+    fn only_lists_and_maps_can_be_indexed() {
+        assert_eq!(
+            run_error("print \"abc\"[0];"),
+            "Only lists and maps can be indexed."
+        );
+    }
+
+    #[test]
+    fn power_is_right_associative() {
+        assert_eq!(run("print 2 ** 3;"), "8.0\n");
+        assert_eq!(run("print 2 ** 3 ** 2;"), "512.0\n");
+        assert_eq!(run("print 4 ** 0.5 == 2;"), "true\n");
+    }
+
+    #[test]
+    fn power_binds_tighter_than_factor_and_looser_than_unary() {
+        assert_eq!(run("print 2 * 3 ** 2;"), "18.0\n");
+        assert_eq!(run("print -2 ** 2;"), "4.0\n");
+    }
+
+    #[test]
+    fn compound_assignment_updates_target() {
+        let source = r#"
+            var x = 1;
+            x += 3;
+            print x;
+            x -= 1;
+            x *= 4;
+            x /= 2;
+            print x;
+            var s = "a";
+            s += "b";
+            print s;
+        "#;
+        assert_eq!(run(source), "4.0\n6.0\nab\n");
+    }
+
+    #[test]
+    fn compound_assignment_on_fields_and_elements() {
+        let source = r#"
+            class Box {}
+            var b = Box();
+            b.n = 1;
+            b.n += 2;
+            var xs = [1, 2];
+            xs[1] *= 5;
+            print b.n;
+            print xs;
+        "#;
+        assert_eq!(run(source), "3.0\n[1.0, 10.0]\n");
+    }
+
+    #[test]
+    fn increment_and_decrement_give_new_value() {
+        let source = r#"
+            var x = 1;
+            print ++x;
+            print x == 2;
+            print --x;
+            var xs = [5];
+            ++xs[0];
+            print xs;
+        "#;
+        assert_eq!(run(source), "2.0\ntrue\n1.0\n[6.0]\n");
+    }
+
+    #[test]
+    fn switch_runs_matching_case_or_default() {
+        let source = r#"
+            fun describe(n) {
+                switch (n) {
+                    case 1: print "one";
+                    case 2: print "two";
+                    case "3": print "three";
+                    default: print "many";
+                }
+            }
+            describe(1);
+            describe(2);
+            describe(3);
+            describe("3");
+        "#;
+        assert_eq!(run(source), "one\ntwo\nmany\nthree\n");
+    }
+
+    #[test]
+    fn switch_without_match_or_default_does_nothing() {
+        assert_eq!(
+            run("switch (5) { case 1: print 1; } print \"done\";"),
+            "done\n"
+        );
+    }
+
+    #[test]
+    fn do_while_runs_body_before_condition() {
+        assert_eq!(run("do print 1; while (false);"), "1.0\n");
+    }
+
+    #[test]
+    fn do_while_supports_break_and_continue() {
+        let source = r#"
+            var i = 0;
+            do {
+                i = i + 1;
+                if (i == 2) continue;
+                if (i == 4) break;
+                print i;
+            } while (i < 10);
+        "#;
+        assert_eq!(run(source), "1.0\n3.0\n");
+    }
+
+    #[test]
+    fn logical_operators_return_the_deciding_operand() {
+        assert_eq!(run("print nil or \"yes\"; print 1 and 2;"), "yes\n2.0\n");
+        assert_eq!(run("print 0 or 1; print nil and 1;"), "0.0\nnil\n");
+    }
+
+    #[test]
+    fn logical_operators_short_circuit() {
+        let source = "print false and missing; print true or missing;";
+        assert_eq!(run(source), "false\ntrue\n");
+    }
+
+    #[test]
+    fn unless_runs_body_when_falsey() {
+        assert_eq!(run("unless (false) print 1;"), "1.0\n");
+        assert_eq!(run("unless (true) print 1; else print 2;"), "2.0\n");
+        assert_eq!(run("unless (nil) print 3;"), "3.0\n");
+        assert_eq!(run("unless (0) print 4;"), "");
+    }
+
+    #[test]
+    fn pi_and_e_are_ordinary_globals() {
+        assert_eq!(run("print PI > 3.14 and PI < 3.15;"), "true\n");
+        assert_eq!(run("print E > 2.71 and E < 2.72;"), "true\n");
+        assert_eq!(run("var PI = 3; print PI;"), "3.0\n");
+    }
+
+    #[test]
+    fn unbounded_recursion_is_a_stack_overflow() {
+        on_interpreter_stack(|| {
+            let source = "fun f(n) { return 1 + f(n + 1); } f(0);";
+            assert_eq!(run_error(source), "Stack overflow.");
+        });
+    }
+
+    #[test]
+    fn recursion_through_nested_loops_is_a_stack_overflow() {
+        on_interpreter_stack(|| {
+            let source = "
+                fun f(n) {
+                    while (true) {
+                        for (var i = 0; i < 1; i = i + 1) {
+                            if (true) { { return 1 + f(n + 1); } }
+                        }
+                    }
+                }
+                f(0);
+            ";
+            assert_eq!(run_error(source), "Stack overflow.");
+        });
+    }
+
+    #[test]
+    fn max_call_depth_is_configurable() {
+        let mut session = Session::new();
+        session.interpreter.set_max_call_depth(3);
+        let source = "fun f(n) { if (n > 0) f(n - 1); } f(2);";
+        assert_eq!(session.run(source), "");
+        assert_eq!(session.run_error("f(3);"), "Stack overflow.");
+    }
+
+    #[test]
+    fn tail_recursion_does_not_overflow() {
+        let source = r#"
+            fun countdown(n) {
+                if (n == 0) return "done";
+                return countdown(n - 1);
+            }
+            print countdown(1000000);
+        "#;
+        assert_eq!(run(source), "done\n");
+    }
+
+    #[test]
+    fn tail_call_does_not_count_towards_depth() {
+        let mut session = Session::new();
+        session.interpreter.set_max_call_depth(2);
+        let source = "fun f(n) { if (n > 0) return f(n - 1); return n; } print f(100);";
+        assert_eq!(session.run(source), "0.0\n");
+    }
+
+    #[test]
+    fn interpolation_splices_in_values() {
+        assert_eq!(run("print \"x=${1+1}\";"), "x=2.0\n");
+        let source = r#"
+            var name = "bob";
+            print "hi ${name + "${"!"}"} ${[1, {"a": 2}]}";
+            print "${nil}${true}";
+        "#;
+        assert_eq!(run(source), "hi bob! [1.0, {a: 2.0}]\nniltrue\n");
+    }
+}
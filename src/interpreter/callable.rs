@@ -0,0 +1,24 @@
+use std::rc::Rc;
+
+use super::{Interpreter, RuntimeError};
+use crate::scanner::{Literal, Token};
+
+// Anything that can be called like a function.
+pub trait Callable {
+    // The number of arguments the callable expects.
+    fn arity(&self) -> usize;
+
+    // How many more arguments than the arity may be passed, which only natives use.
+    fn optional(&self) -> usize {
+        0
+    }
+
+    /* Takes the shared pointer so that a class can hand itself to the instances it
+    creates. The closing parenthesis of the call is where errors are reported. */
+    fn call(
+        self: Rc<Self>,
+        interpreter: &mut Interpreter,
+        paren: &Token,
+        arguments: Vec<Literal>,
+    ) -> Result<Literal, RuntimeError>;
+}
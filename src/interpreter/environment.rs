@@ -0,0 +1,117 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use super::RuntimeError;
+use crate::scanner::{Literal, Token};
+
+// Stores the bindings of variable names to their values.
+pub struct Environment {
+    enclosing: Option<Rc<RefCell<Environment>>>,
+    values: HashMap<String, Literal>,
+}
+
+impl Environment {
+    // The outermost environment, which holds the globals.
+    pub fn new() -> Self {
+        Environment {
+            enclosing: None,
+            values: HashMap::new(),
+        }
+    }
+
+    // A local scope nested inside of the given environment.
+    pub fn with_enclosing(enclosing: Rc<RefCell<Environment>>) -> Self {
+        Environment {
+            enclosing: Some(enclosing),
+            values: HashMap::new(),
+        }
+    }
+
+    // The variables bound directly in this scope, not including the enclosing ones.
+    pub fn values(&self) -> &HashMap<String, Literal> {
+        &self.values
+    }
+
+    // Bind a new variable. Redefining an existing variable replaces its value.
+    pub fn define(&mut self, name: String, value: Literal) {
+        self.values.insert(name, value);
+    }
+
+    // Look up the value bound to the variable, starting from the innermost scope.
+    pub fn get(&self, name: &Token) -> Result<Literal, RuntimeError> {
+        if let Some(value) = self.values.get(&name.lexeme) {
+            return Ok(value.clone());
+        }
+
+        match &self.enclosing {
+            Some(enclosing) => enclosing.borrow().get(name),
+            None => Err(undefined(name)),
+        }
+    }
+
+    // Look up the variable in the scope the given number of hops out from this one.
+    pub fn get_at(&self, distance: usize, name: &Token) -> Result<Literal, RuntimeError> {
+        if distance == 0 {
+            return self
+                .values
+                .get(&name.lexeme)
+                .cloned()
+                .ok_or_else(|| undefined(name));
+        }
+
+        match &self.enclosing {
+            Some(enclosing) => enclosing.borrow().get_at(distance - 1, name),
+            None => Err(undefined(name)),
+        }
+    }
+
+    // Look up a variable bound in this scope only.
+    pub fn get_local(&self, name: &str) -> Option<Literal> {
+        self.values.get(name).cloned()
+    }
+
+    /* Rebind the nearest existing variable with the name.
+    Assignment is not allowed to create a new variable. */
+    pub fn assign(&mut self, name: &Token, value: Literal) -> Result<(), RuntimeError> {
+        if let Some(slot) = self.values.get_mut(&name.lexeme) {
+            *slot = value;
+            return Ok(());
+        }
+
+        match &self.enclosing {
+            Some(enclosing) => enclosing.borrow_mut().assign(name, value),
+            None => Err(undefined(name)),
+        }
+    }
+
+    // Rebind the variable in the scope the given number of hops out from this one.
+    pub fn assign_at(
+        &mut self,
+        distance: usize,
+        name: &Token,
+        value: Literal,
+    ) -> Result<(), RuntimeError> {
+        if distance == 0 {
+            return match self.values.get_mut(&name.lexeme) {
+                Some(slot) => {
+                    *slot = value;
+                    Ok(())
+                }
+                None => Err(undefined(name)),
+            };
+        }
+
+        match &self.enclosing {
+            Some(enclosing) => enclosing.borrow_mut().assign_at(distance - 1, name, value),
+            None => Err(undefined(name)),
+        }
+    }
+}
+
+fn undefined(name: &Token) -> RuntimeError {
+    RuntimeError::new(
+        name.clone(),
+        format!("Undefined variable '{}'.", name.lexeme),
+    )
+}
@@ -0,0 +1,105 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt;
+use std::rc::Rc;
+
+use super::{Callable, Interpreter, LoxFunction, LoxInstance, RuntimeError};
+use crate::scanner::{Literal, Token};
+
+// A class declared in Lox code. Calling it creates a new instance.
+pub struct LoxClass {
+    name: String,
+    superclass: Option<Rc<LoxClass>>,
+    methods: HashMap<String, Rc<LoxFunction>>,
+    fields: Option<Vec<String>>, // The fields of a record, which can't change once created.
+}
+
+impl LoxClass {
+    pub fn new(
+        name: String,
+        superclass: Option<Rc<LoxClass>>,
+        methods: HashMap<String, Rc<LoxFunction>>,
+    ) -> Self {
+        LoxClass {
+            name,
+            superclass,
+            methods,
+            fields: None,
+        }
+    }
+
+    // A record class, whose constructor takes a value for each of the fields.
+    pub fn record(name: String, fields: Vec<String>) -> Self {
+        LoxClass {
+            name,
+            superclass: None,
+            methods: HashMap::new(),
+            fields: Some(fields),
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn fields(&self) -> Option<&[String]> {
+        self.fields.as_deref()
+    }
+
+    // Methods not defined on this class are inherited from the superclass.
+    pub fn find_method(&self, name: &str) -> Option<Rc<LoxFunction>> {
+        match self.methods.get(name) {
+            Some(method) => Some(Rc::clone(method)),
+            None => self.superclass.as_ref()?.find_method(name),
+        }
+    }
+}
+
+impl Callable for LoxClass {
+    // A class takes the same arguments as its initializer.
+    fn arity(&self) -> usize {
+        if let Some(fields) = &self.fields {
+            return fields.len();
+        }
+
+        match self.find_method("init") {
+            Some(initializer) => initializer.arity(),
+            None => 0,
+        }
+    }
+
+    fn call(
+        self: Rc<Self>,
+        interpreter: &mut Interpreter,
+        paren: &Token,
+        arguments: Vec<Literal>,
+    ) -> Result<Literal, RuntimeError> {
+        // A record's constructor sets each field to the argument in the same position.
+        if let Some(fields) = &self.fields {
+            let values = fields.iter().cloned().zip(arguments).collect();
+            let instance = LoxInstance::new(Rc::clone(&self), values);
+            return Ok(Literal::Instance(Rc::new(RefCell::new(instance))));
+        }
+
+        let initializer = self.find_method("init");
+        let instance = Rc::new(RefCell::new(LoxInstance::new(self, HashMap::new())));
+
+        if let Some(initializer) = initializer {
+            Rc::new(initializer.bind(Rc::clone(&instance))).call(interpreter, paren, arguments)?;
+        }
+
+        Ok(Literal::Instance(instance))
+    }
+}
+
+impl fmt::Display for LoxClass {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "<class {}>", self.name)
+    }
+}
+
+impl fmt::Debug for LoxClass {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self)
+    }
+}
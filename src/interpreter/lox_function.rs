@@ -0,0 +1,141 @@
+use std::cell::RefCell;
+use std::fmt;
+use std::mem;
+use std::rc::Rc;
+
+use super::{Callable, Environment, Interpreter, LoxInstance, RuntimeError, Unwind};
+use crate::expression::Expression;
+use crate::scanner::{Literal, Token};
+use crate::statement::Statement;
+
+// A function declared in Lox code.
+pub struct LoxFunction {
+    name: Option<Token>, // Lambdas don't have a name.
+    params: Vec<Token>,
+    guard: Option<Rc<dyn Expression>>,
+    body: Rc<Vec<Box<dyn Statement>>>,
+    closure: Rc<RefCell<Environment>>, // The scope the function was declared in.
+    is_initializer: bool,
+}
+
+impl LoxFunction {
+    pub fn new(
+        name: Option<Token>,
+        params: Vec<Token>,
+        guard: Option<Rc<dyn Expression>>,
+        body: Rc<Vec<Box<dyn Statement>>>,
+        closure: Rc<RefCell<Environment>>,
+        is_initializer: bool,
+    ) -> Self {
+        LoxFunction {
+            name,
+            params,
+            guard,
+            body,
+            closure,
+            is_initializer,
+        }
+    }
+
+    // Create a copy of the method whose body sees 'this' as the given instance.
+    pub fn bind(&self, instance: Rc<RefCell<LoxInstance>>) -> LoxFunction {
+        let mut environment = Environment::with_enclosing(Rc::clone(&self.closure));
+        environment.define(String::from("this"), Literal::Instance(instance));
+
+        LoxFunction::new(
+            self.name.clone(),
+            self.params.clone(),
+            self.guard.clone(),
+            Rc::clone(&self.body),
+            Rc::new(RefCell::new(environment)),
+            self.is_initializer,
+        )
+    }
+
+    // The instance an initializer was bound to, which is what calling it returns.
+    fn this(&self) -> Literal {
+        self.closure
+            .borrow()
+            .get_local("this")
+            .expect("Initializers are only called once bound to an instance.")
+    }
+}
+
+impl Callable for LoxFunction {
+    fn arity(&self) -> usize {
+        self.params.len()
+    }
+
+    /* Run the body in a new environment with each parameter bound to its argument.
+    The environment encloses the closure rather than the caller's scope, so the body
+    sees the variables that were in scope where the function was declared. A tail
+    call to itself runs the body again here with the new arguments. */
+    fn call(
+        self: Rc<Self>,
+        interpreter: &mut Interpreter,
+        _paren: &Token,
+        arguments: Vec<Literal>,
+    ) -> Result<Literal, RuntimeError> {
+        let mut arguments = arguments;
+        loop {
+            let mut environment = Environment::with_enclosing(Rc::clone(&self.closure));
+            for (param, argument) in self.params.iter().zip(arguments) {
+                environment.define(param.lexeme.clone(), argument);
+            }
+            let environment = Rc::new(RefCell::new(environment));
+
+            // The guard sees the parameters, so it's evaluated in the same scope as the body.
+            if let Some(guard) = &self.guard {
+                let previous = mem::replace(&mut interpreter.environment, Rc::clone(&environment));
+                let passed = interpreter.evaluate(guard.as_ref());
+                interpreter.environment = previous;
+
+                if !interpreter.is_truthy(&passed?) {
+                    let name = self
+                        .name
+                        .as_ref()
+                        .expect("Only declared functions can have a guard.");
+                    return Err(RuntimeError::new(
+                        name.clone(),
+                        format!("Guard failed for {}.", name.lexeme),
+                    ));
+                }
+            }
+
+            let previous = interpreter.function.replace(Rc::clone(&self));
+            let result = interpreter.execute_block(&self.body, environment);
+            interpreter.function = previous;
+
+            // Falling off the end of the body returns nil.
+            return match result {
+                Ok(()) | Err(Unwind::Return(_)) if self.is_initializer => Ok(self.this()),
+                Ok(()) => Ok(Literal::Nil),
+                Err(Unwind::Return(value)) => Ok(value),
+                Err(Unwind::TailCall(next)) => {
+                    arguments = next;
+                    continue;
+                }
+                Err(Unwind::Error(error)) => Err(error),
+                Err(Unwind::Break) | Err(Unwind::Continue) => {
+                    unreachable!("The parser only allows 'break' and 'continue' inside a loop.")
+                }
+            };
+        }
+    }
+}
+
+// Shows the name and arity, such as <fn add/2>, or <fn/1> for a lambda.
+impl fmt::Display for LoxFunction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.name {
+            Some(name) => write!(f, "<fn {}/{}>", name.lexeme, self.arity()),
+            None => write!(f, "<fn/{}>", self.arity()),
+        }
+    }
+}
+
+impl fmt::Debug for LoxFunction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self)
+    }
+}
@@ -0,0 +1,77 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt;
+use std::rc::Rc;
+
+use super::{LoxClass, RuntimeError};
+use crate::scanner::{Literal, Token};
+
+// An instance of a class, holding its own fields.
+pub struct LoxInstance {
+    class: Rc<LoxClass>,
+    fields: HashMap<String, Literal>,
+}
+
+impl LoxInstance {
+    pub fn new(class: Rc<LoxClass>, fields: HashMap<String, Literal>) -> Self {
+        LoxInstance { class, fields }
+    }
+
+    pub fn class(&self) -> &Rc<LoxClass> {
+        &self.class
+    }
+
+    // The values of a record's fields in the order they were declared, or None if it isn't one.
+    pub fn record_values(&self) -> Option<Vec<Literal>> {
+        let fields = self.class.fields()?;
+        Some(
+            fields
+                .iter()
+                .map(|field| self.fields[field].clone())
+                .collect(),
+        )
+    }
+
+    /* Fields shadow methods with the same name. A method is bound to the instance
+    it was looked up on, which is why this takes the shared instance. */
+    pub fn get(instance: &Rc<RefCell<LoxInstance>>, name: &Token) -> Result<Literal, RuntimeError> {
+        let instance_ref = instance.borrow();
+        if let Some(value) = instance_ref.fields.get(&name.lexeme) {
+            return Ok(value.clone());
+        }
+
+        match instance_ref.class.find_method(&name.lexeme) {
+            Some(method) => Ok(Literal::Function(Rc::new(method.bind(Rc::clone(instance))))),
+            None => Err(RuntimeError::new(
+                name.clone(),
+                format!("Undefined property '{}'.", name.lexeme),
+            )),
+        }
+    }
+
+    /* Fields don't need to be declared, so setting one may also create it.
+    Records are immutable, so their fields can't be set at all. */
+    pub fn set(&mut self, name: &Token, value: Literal) -> Result<(), RuntimeError> {
+        if self.class.fields().is_some() {
+            return Err(RuntimeError::new(
+                name.clone(),
+                format!("Can't assign to field '{}' of a record.", name.lexeme),
+            ));
+        }
+
+        self.fields.insert(name.lexeme.clone(), value);
+        Ok(())
+    }
+}
+
+impl fmt::Display for LoxInstance {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} instance", self.class.name())
+    }
+}
+
+impl fmt::Debug for LoxInstance {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self)
+    }
+}
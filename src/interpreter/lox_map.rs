@@ -0,0 +1,42 @@
+use std::collections::HashMap;
+
+use crate::scanner::{Literal, LiteralKey};
+
+/* A map from keys to values that remembers the order its keys were first added,
+so printing one always shows its entries the same way. A key mapped to nil is
+still in the map, which is how get() and has() tell it apart from a missing key. */
+#[derive(Debug, Default)]
+pub struct LoxMap {
+    entries: Vec<(LiteralKey, Literal)>,
+    indices: HashMap<LiteralKey, usize>, // Where each key's entry is.
+}
+
+impl LoxMap {
+    pub fn new() -> Self {
+        LoxMap::default()
+    }
+
+    pub fn get(&self, key: &LiteralKey) -> Option<&Literal> {
+        self.indices.get(key).map(|index| &self.entries[*index].1)
+    }
+
+    pub fn contains_key(&self, key: &LiteralKey) -> bool {
+        self.indices.contains_key(key)
+    }
+
+    // Replace the value of a key already in the map, or add it at the end.
+    pub fn insert(&mut self, key: LiteralKey, value: Literal) {
+        match self.indices.get(&key) {
+            Some(index) => self.entries[*index].1 = value,
+            None => {
+                self.indices.insert(key.clone(), self.entries.len());
+                self.entries.push((key, value));
+            }
+        }
+    }
+
+    // The entries in the order their keys were first added.
+    pub fn entries(&self) -> &[(LiteralKey, Literal)] {
+        &self.entries
+    }
+}
@@ -0,0 +1,65 @@
+use std::fmt;
+use std::rc::Rc;
+
+use super::{Callable, Interpreter, RuntimeError};
+use crate::scanner::{Literal, Token};
+
+/* The Rust side of a native function. An error is just the message, since the
+native doesn't know where in the script it was called from. */
+pub type Native = fn(&mut Interpreter, &[Literal]) -> Result<Literal, String>;
+
+// A function implemented in Rust that Lox code can call.
+pub struct NativeFunction {
+    name: String,
+    arity: usize,
+    optional: usize,
+    function: Native,
+}
+
+impl NativeFunction {
+    pub fn new(name: String, arity: usize, function: Native) -> Self {
+        NativeFunction::with_optional(name, arity, 0, function)
+    }
+
+    // A native that also accepts up to the given number of arguments after the required ones.
+    pub fn with_optional(name: String, arity: usize, optional: usize, function: Native) -> Self {
+        NativeFunction {
+            name,
+            arity,
+            optional,
+            function,
+        }
+    }
+}
+
+impl Callable for NativeFunction {
+    fn arity(&self) -> usize {
+        self.arity
+    }
+
+    fn optional(&self) -> usize {
+        self.optional
+    }
+
+    fn call(
+        self: Rc<Self>,
+        interpreter: &mut Interpreter,
+        paren: &Token,
+        arguments: Vec<Literal>,
+    ) -> Result<Literal, RuntimeError> {
+        (self.function)(interpreter, &arguments)
+            .map_err(|message| RuntimeError::new(paren.clone(), message))
+    }
+}
+
+impl fmt::Display for NativeFunction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "<native {}>", self.name)
+    }
+}
+
+impl fmt::Debug for NativeFunction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self)
+    }
+}
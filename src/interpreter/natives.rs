@@ -0,0 +1,702 @@
+use std::cell::RefCell;
+use std::io::{self, BufRead, Write};
+use std::rc::Rc;
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use super::{Environment, Interpreter, LoxMap, Native, NativeFunction};
+use crate::scanner::{Literal, LiteralKey, Token, TokenType};
+
+// Bind every native function into the environment, which should be the globals.
+pub fn define(environment: &mut Environment) {
+    define_native(environment, "abs", 1, abs);
+    define_native_with_optional(environment, "assert", 1, 1, assert);
+    define_native(environment, "ceil", 1, ceil);
+    define_native(environment, "clock", 0, clock);
+    define_native(environment, "charAt", 2, char_at);
+    define_native(environment, "echo", 1, echo);
+    define_native(environment, "filter", 2, filter);
+    define_native(environment, "floor", 1, floor);
+    define_native(environment, "get", 3, get);
+    define_native(environment, "has", 2, has);
+    define_native(environment, "len", 1, len);
+    define_native(environment, "length", 1, length);
+    define_native(environment, "map", 2, map);
+    define_native(environment, "max", 2, max);
+    define_native(environment, "min", 2, min);
+    define_native(environment, "num", 1, number);
+    define_native(environment, "number", 1, number);
+    define_native(environment, "pop", 1, pop);
+    define_native(environment, "pow", 2, pow);
+    define_native(environment, "push", 2, push);
+    define_native(environment, "readLine", 0, read_line);
+    define_native_with_optional(environment, "reduce", 2, 1, reduce);
+    define_native(environment, "round", 1, round);
+    define_native(environment, "sleep", 1, sleep);
+    define_native(environment, "sqrt", 1, sqrt);
+    define_native(environment, "string", 1, string);
+    define_native(environment, "substr", 3, substr);
+    define_native(environment, "typeof", 1, type_of);
+}
+
+fn define_native(environment: &mut Environment, name: &str, arity: usize, function: Native) {
+    let native = NativeFunction::new(String::from(name), arity, function);
+    environment.define(String::from(name), Literal::Native(Rc::new(native)));
+}
+
+fn define_native_with_optional(
+    environment: &mut Environment,
+    name: &str,
+    arity: usize,
+    optional: usize,
+    function: Native,
+) {
+    let native = NativeFunction::with_optional(String::from(name), arity, optional, function);
+    environment.define(String::from(name), Literal::Native(Rc::new(native)));
+}
+
+fn abs(_interpreter: &mut Interpreter, arguments: &[Literal]) -> Result<Literal, String> {
+    let value = number_argument("abs", &arguments[0])?;
+    Ok(Literal::Number(value.abs()))
+}
+
+// Fail with the message, if one was given, when the condition is falsey.
+fn assert(interpreter: &mut Interpreter, arguments: &[Literal]) -> Result<Literal, String> {
+    if interpreter.is_truthy(&arguments[0]) {
+        return Ok(Literal::Nil);
+    }
+    match arguments.get(1) {
+        Some(message) => Err(interpreter.stringify(message.clone())),
+        None => Err(String::from("Assertion failed.")),
+    }
+}
+
+fn ceil(_interpreter: &mut Interpreter, arguments: &[Literal]) -> Result<Literal, String> {
+    let value = number_argument("ceil", &arguments[0])?;
+    Ok(Literal::Number(value.ceil()))
+}
+
+/* The character at the index of a string, as a string of its own. Like len(),
+indices count characters rather than bytes. */
+fn char_at(_interpreter: &mut Interpreter, arguments: &[Literal]) -> Result<Literal, String> {
+    let chars = string_argument("charAt", &arguments[0])?;
+    let index = index_argument("charAt", &arguments[1])?;
+    match chars.get(index) {
+        Some(c) => Ok(Literal::String(c.to_string())),
+        None => Err(format!(
+            "Index {} is out of range for a string of length {}.",
+            index,
+            chars.len()
+        )),
+    }
+}
+
+// Seconds since the Unix epoch, mostly useful for timing code.
+fn clock(_interpreter: &mut Interpreter, _arguments: &[Literal]) -> Result<Literal, String> {
+    match SystemTime::now().duration_since(UNIX_EPOCH) {
+        Ok(duration) => Ok(Literal::Number(duration.as_secs_f64())),
+        Err(_) => Ok(Literal::Number(0.0)),
+    }
+}
+
+// Print a value like the print statement does, but hand it back so it can be used in an expression.
+fn echo(interpreter: &mut Interpreter, arguments: &[Literal]) -> Result<Literal, String> {
+    let text = interpreter.stringify(arguments[0].clone());
+    writeln!(interpreter.output, "{}", text).expect("Failed to write output.");
+    Ok(arguments[0].clone())
+}
+
+// A new list of the elements the function returns something truthy for.
+fn filter(interpreter: &mut Interpreter, arguments: &[Literal]) -> Result<Literal, String> {
+    let elements = list_argument("filter", &arguments[0])?.borrow().clone();
+    let function = function_argument("filter", &arguments[1])?;
+    let mut kept = Vec::new();
+    for element in elements {
+        let keep = call_function("filter", interpreter, &function, vec![element.clone()])?;
+        if interpreter.is_truthy(&keep) {
+            kept.push(element);
+        }
+    }
+    Ok(Literal::List(Rc::new(RefCell::new(kept))))
+}
+
+fn floor(_interpreter: &mut Interpreter, arguments: &[Literal]) -> Result<Literal, String> {
+    let value = number_argument("floor", &arguments[0])?;
+    Ok(Literal::Number(value.floor()))
+}
+
+// The value of the key, or the default only when the key isn't in the map at all.
+fn get(_interpreter: &mut Interpreter, arguments: &[Literal]) -> Result<Literal, String> {
+    let map = map_argument("get", &arguments[0])?;
+    let key = LiteralKey::new(arguments[1].clone())?;
+    let value = map.borrow().get(&key).cloned();
+    Ok(value.unwrap_or_else(|| arguments[2].clone()))
+}
+
+// Whether the key is in the map, even if it's mapped to nil.
+fn has(_interpreter: &mut Interpreter, arguments: &[Literal]) -> Result<Literal, String> {
+    let map = map_argument("has", &arguments[0])?;
+    let key = LiteralKey::new(arguments[1].clone())?;
+    let has = map.borrow().contains_key(&key);
+    Ok(Literal::Bool(has))
+}
+
+// The number of characters in a string, rather than the number of bytes.
+fn len(_interpreter: &mut Interpreter, arguments: &[Literal]) -> Result<Literal, String> {
+    match &arguments[0] {
+        Literal::String(value) => Ok(Literal::Number(value.chars().count() as f64)),
+        _ => Err(String::from("Argument to len() must be a string.")),
+    }
+}
+
+// The number of elements in a list.
+fn length(_interpreter: &mut Interpreter, arguments: &[Literal]) -> Result<Literal, String> {
+    let list = list_argument("length", &arguments[0])?;
+    let length = list.borrow().len();
+    Ok(Literal::Number(length as f64))
+}
+
+// A new list of what the function returns for each element.
+fn map(interpreter: &mut Interpreter, arguments: &[Literal]) -> Result<Literal, String> {
+    let elements = list_argument("map", &arguments[0])?.borrow().clone();
+    let function = function_argument("map", &arguments[1])?;
+    let mut mapped = Vec::new();
+    for element in elements {
+        mapped.push(call_function("map", interpreter, &function, vec![element])?);
+    }
+    Ok(Literal::List(Rc::new(RefCell::new(mapped))))
+}
+
+fn max(_interpreter: &mut Interpreter, arguments: &[Literal]) -> Result<Literal, String> {
+    let (a, b) = number_arguments("max", arguments)?;
+    Ok(Literal::Number(a.max(b)))
+}
+
+fn min(_interpreter: &mut Interpreter, arguments: &[Literal]) -> Result<Literal, String> {
+    let (a, b) = number_arguments("min", arguments)?;
+    Ok(Literal::Number(a.min(b)))
+}
+
+/* Parse a string into a number, ignoring surrounding whitespace. Numbers are left
+as they are. Also available as num(). */
+fn number(_interpreter: &mut Interpreter, arguments: &[Literal]) -> Result<Literal, String> {
+    match &arguments[0] {
+        Literal::Number(value) => Ok(Literal::Number(*value)),
+        Literal::String(value) => match parse_number(value.trim()) {
+            Some(number) => Ok(Literal::Number(number)),
+            None => Err(format!("Can't convert '{}' to a number.", value)),
+        },
+        _ => Err(String::from(
+            "Argument to number() must be a string or a number.",
+        )),
+    }
+}
+
+/* A sign, digits, a decimal point and an exponent. Rust would also take spellings
+like "inf" and "NaN", and too large a number comes out as infinity, but neither is
+a number a Lox program can write. */
+fn parse_number(text: &str) -> Option<f64> {
+    let allowed = |c: char| c.is_ascii_digit() || matches!(c, '+' | '-' | '.' | 'e' | 'E');
+    if !text.chars().all(allowed) {
+        return None;
+    }
+    text.parse::<f64>().ok().filter(|number| number.is_finite())
+}
+
+// Remove the last element of a list and return it.
+fn pop(_interpreter: &mut Interpreter, arguments: &[Literal]) -> Result<Literal, String> {
+    let list = list_argument("pop", &arguments[0])?;
+    let last = list.borrow_mut().pop();
+    last.ok_or_else(|| String::from("Can't pop from an empty list."))
+}
+
+fn pow(_interpreter: &mut Interpreter, arguments: &[Literal]) -> Result<Literal, String> {
+    let (base, exponent) = number_arguments("pow", arguments)?;
+    Ok(Literal::Number(base.powf(exponent)))
+}
+
+// Add a value to the end of a list.
+fn push(_interpreter: &mut Interpreter, arguments: &[Literal]) -> Result<Literal, String> {
+    let list = list_argument("push", &arguments[0])?;
+    list.borrow_mut().push(arguments[1].clone());
+    Ok(Literal::Nil)
+}
+
+/* The next line of input without its line ending, or nil once the input runs out.
+Stdin is only locked for the read, so that its buffer is shared with the prompt and
+with a script read from stdin, rather than a second buffer reading lines ahead. */
+fn read_line(interpreter: &mut Interpreter, _arguments: &[Literal]) -> Result<Literal, String> {
+    let mut line = String::new();
+    let read = match &mut interpreter.input {
+        Some(input) => input.read_line(&mut line),
+        None => io::stdin().lock().read_line(&mut line),
+    };
+    match read {
+        Ok(0) => Ok(Literal::Nil),
+        Ok(_) => {
+            if line.ends_with('\n') {
+                line.pop();
+                if line.ends_with('\r') {
+                    line.pop();
+                }
+            }
+            Ok(Literal::String(line))
+        }
+        Err(error) => Err(format!("Couldn't read input: {}.", error)),
+    }
+}
+
+/* Combine the elements from first to last, passing the function the result so far
+and the next element. Without an initial value the first element is the start. */
+fn reduce(interpreter: &mut Interpreter, arguments: &[Literal]) -> Result<Literal, String> {
+    let elements = list_argument("reduce", &arguments[0])?.borrow().clone();
+    let function = function_argument("reduce", &arguments[1])?;
+    let mut elements = elements.into_iter();
+    let mut result = match arguments.get(2) {
+        Some(initial) => initial.clone(),
+        None => elements
+            .next()
+            .ok_or_else(|| String::from("Can't reduce an empty list without an initial value."))?,
+    };
+    for element in elements {
+        result = call_function("reduce", interpreter, &function, vec![result, element])?;
+    }
+    Ok(result)
+}
+
+// Halfway cases round away from zero, so round(2.5) is 3 and round(-2.5) is -3.
+fn round(_interpreter: &mut Interpreter, arguments: &[Literal]) -> Result<Literal, String> {
+    let value = number_argument("round", &arguments[0])?;
+    Ok(Literal::Number(value.round()))
+}
+
+// Block for the number of milliseconds, which may have a fractional part.
+fn sleep(_interpreter: &mut Interpreter, arguments: &[Literal]) -> Result<Literal, String> {
+    match &arguments[0] {
+        Literal::Number(milliseconds) if *milliseconds >= 0.0 && milliseconds.is_finite() => {
+            match Duration::try_from_secs_f64(milliseconds / 1000.0) {
+                Ok(duration) => thread::sleep(duration),
+                Err(_) => return Err(String::from("Argument to sleep() is too long.")),
+            }
+            Ok(Literal::Nil)
+        }
+        _ => Err(String::from(
+            "Argument to sleep() must be a non-negative number.",
+        )),
+    }
+}
+
+// Negative numbers are an error rather than NaN, which would only surface later.
+fn sqrt(_interpreter: &mut Interpreter, arguments: &[Literal]) -> Result<Literal, String> {
+    let value = number_argument("sqrt", &arguments[0])?;
+    if value < 0.0 {
+        return Err(format!("Can't take the square root of {}.", value));
+    }
+    Ok(Literal::Number(value.sqrt()))
+}
+
+// Any value as a string, exactly as print would show it.
+fn string(interpreter: &mut Interpreter, arguments: &[Literal]) -> Result<Literal, String> {
+    Ok(Literal::String(interpreter.stringify(arguments[0].clone())))
+}
+
+// The characters of a string from the start index up to but not including the end index.
+fn substr(_interpreter: &mut Interpreter, arguments: &[Literal]) -> Result<Literal, String> {
+    let chars = string_argument("substr", &arguments[0])?;
+    let start = index_argument("substr", &arguments[1])?;
+    let end = index_argument("substr", &arguments[2])?;
+    if start > end || end > chars.len() {
+        return Err(format!(
+            "Range {}..{} is out of range for a string of length {}.",
+            start,
+            end,
+            chars.len()
+        ));
+    }
+    Ok(Literal::String(chars[start..end].iter().collect()))
+}
+
+// The name of the kind of value, such as "number" or "instance".
+fn type_of(_interpreter: &mut Interpreter, arguments: &[Literal]) -> Result<Literal, String> {
+    let name = match &arguments[0] {
+        Literal::Bool(_) => "bool",
+        Literal::Number(_) => "number",
+        Literal::String(_) => "string",
+        Literal::Function(_) | Literal::Native(_) => "function",
+        Literal::Class(_) => "class",
+        Literal::Instance(_) => "instance",
+        Literal::List(_) => "list",
+        Literal::Map(_) => "map",
+        Literal::Nil => "nil",
+    };
+    Ok(Literal::String(String::from(name)))
+}
+
+fn function_argument(name: &str, value: &Literal) -> Result<Literal, String> {
+    match value {
+        Literal::Function(_) | Literal::Native(_) | Literal::Class(_) => Ok(value.clone()),
+        _ => Err(format!("Second argument to {}() must be a function.", name)),
+    }
+}
+
+/* Call a function passed to a native, which checks the number of arguments it takes.
+A native doesn't know where it was called from, so an error inside the function is
+reported at the native's call. */
+fn call_function(
+    name: &str,
+    interpreter: &mut Interpreter,
+    function: &Literal,
+    arguments: Vec<Literal>,
+) -> Result<Literal, String> {
+    let paren = Token::new(
+        TokenType::IDENTIFIER,
+        String::from(name),
+        Literal::Nil,
+        0,
+        0,
+    );
+    interpreter
+        .call(function.clone(), &paren, arguments)
+        .map_err(|error| error.message)
+}
+
+// The list itself rather than a copy, so changes are seen by everything holding it.
+fn list_argument(name: &str, value: &Literal) -> Result<Rc<RefCell<Vec<Literal>>>, String> {
+    match value {
+        Literal::List(list) => Ok(Rc::clone(list)),
+        _ => Err(format!("First argument to {}() must be a list.", name)),
+    }
+}
+
+fn map_argument(name: &str, value: &Literal) -> Result<Rc<RefCell<LoxMap>>, String> {
+    match value {
+        Literal::Map(map) => Ok(Rc::clone(map)),
+        _ => Err(format!("First argument to {}() must be a map.", name)),
+    }
+}
+
+fn number_argument(name: &str, value: &Literal) -> Result<f64, String> {
+    match value {
+        Literal::Number(value) => Ok(*value),
+        _ => Err(format!("Argument to {}() must be a number.", name)),
+    }
+}
+
+fn number_arguments(name: &str, arguments: &[Literal]) -> Result<(f64, f64), String> {
+    match (&arguments[0], &arguments[1]) {
+        (Literal::Number(a), Literal::Number(b)) => Ok((*a, *b)),
+        _ => Err(format!("Arguments to {}() must be numbers.", name)),
+    }
+}
+
+fn string_argument(name: &str, value: &Literal) -> Result<Vec<char>, String> {
+    match value {
+        Literal::String(value) => Ok(value.chars().collect()),
+        _ => Err(format!("First argument to {}() must be a string.", name)),
+    }
+}
+
+// Indices must be whole numbers, and negative ones aren't counted from the end.
+fn index_argument(name: &str, value: &Literal) -> Result<usize, String> {
+    match value {
+        Literal::Number(index) if *index >= 0.0 && index.fract() == 0.0 => Ok(*index as usize),
+        _ => Err(format!(
+            "Index to {}() must be a non-negative integer.",
+            name
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::Interpreter;
+    use crate::scanner::Literal;
+    use crate::testing::{run, run_error};
+
+    #[test]
+    fn get_tells_nil_value_from_absent_key() {
+        let source = r#"
+            var m = {"present": nil};
+            print m["present"];
+            print m["absent"];
+            print get(m, "present", "default");
+            print get(m, "absent", "default");
+            print has(m, "present");
+            print has(m, "absent");
+        "#;
+        assert_eq!(run(source), "nil\nnil\nnil\ndefault\ntrue\nfalse\n");
+    }
+
+    #[test]
+    fn get_needs_a_map() {
+        assert_eq!(
+            run_error("get([], 1, nil);"),
+            "First argument to get() must be a map."
+        );
+    }
+
+    #[test]
+    fn num_accepts_signs_whitespace_and_exponents() {
+        let source = r#"
+            print num("+5");
+            print num("-5");
+            print num(" 3.5 ");
+            print num("1e3");
+        "#;
+        assert_eq!(run(source), "5.0\n-5.0\n3.5\n1000.0\n");
+    }
+
+    #[test]
+    fn num_rejects_what_is_not_a_number() {
+        for text in &["abc", "nan", "inf", "1e999"] {
+            assert_eq!(
+                run_error(&format!("num(\"{}\");", text)),
+                format!("Can't convert '{}' to a number.", text)
+            );
+        }
+    }
+
+    #[test]
+    fn clock_returns_a_number() {
+        let time = super::clock(&mut Interpreter::new(), &[]);
+        assert!(matches!(time, Ok(Literal::Number(_))));
+    }
+
+    #[test]
+    fn native_arity_is_checked() {
+        assert_eq!(run_error("clock(1);"), "Expected 0 arguments but got 1.");
+    }
+
+    #[test]
+    fn map_doubles_each_element() {
+        assert_eq!(
+            run("print map([1, 2, 3], fun (x) { return x * 2; });"),
+            "[2.0, 4.0, 6.0]\n"
+        );
+    }
+
+    #[test]
+    fn filter_keeps_even_elements() {
+        assert_eq!(
+            run("print filter([1, 2, 3, 4], fun (x) { return x % 2 == 0; });"),
+            "[2.0, 4.0]\n"
+        );
+    }
+
+    #[test]
+    fn reduce_sums_elements() {
+        let add = "fun (total, x) { return total + x; }";
+        assert_eq!(
+            run(&format!("print reduce([1, 2, 3, 4], {}, 0);", add)),
+            "10.0\n"
+        );
+        assert_eq!(run(&format!("print reduce([1, 2, 3], {});", add)), "6.0\n");
+    }
+
+    #[test]
+    fn map_checks_its_function() {
+        assert_eq!(
+            run_error("map([1], 3);"),
+            "Second argument to map() must be a function."
+        );
+        assert_eq!(
+            run_error("map([1], fun (a, b) {});"),
+            "Expected 2 arguments but got 1."
+        );
+    }
+
+    #[test]
+    fn len_counts_characters() {
+        assert_eq!(
+            run(r#"print len("hello") == 5; print len("héllo") == 5;"#),
+            "true\ntrue\n"
+        );
+    }
+
+    #[test]
+    fn len_needs_one_string() {
+        assert_eq!(run_error("len(5);"), "Argument to len() must be a string.");
+        assert_eq!(
+            run_error(r#"len("a", "b");"#),
+            "Expected 1 arguments but got 2."
+        );
+    }
+
+    #[test]
+    fn substr_and_char_at() {
+        let source = r#"
+            print substr("hello", 1, 4);
+            print charAt("hello", 0);
+            print substr("héllo", 1, 3);
+            print charAt("héllo", 1);
+        "#;
+        assert_eq!(run(source), "ell\nh\nél\né\n");
+    }
+
+    #[test]
+    fn substr_and_char_at_check_bounds() {
+        assert_eq!(
+            run_error(r#"charAt("abc", 3);"#),
+            "Index 3 is out of range for a string of length 3."
+        );
+        assert_eq!(
+            run_error(r#"substr("abc", 1, 4);"#),
+            "Range 1..4 is out of range for a string of length 3."
+        );
+        assert_eq!(
+            run_error(r#"charAt("abc", -1);"#),
+            "Index to charAt() must be a non-negative integer."
+        );
+    }
+
+    #[test]
+    fn number_and_string_conversions() {
+        let source = r#"
+            print number("3.5") == 3.5;
+            print number(2) == 2;
+            print string(42) == "42.0";
+            print string(nil);
+        "#;
+        assert_eq!(run(source), "true\ntrue\ntrue\nnil\n");
+    }
+
+    #[test]
+    fn number_rejects_unparseable_string() {
+        assert_eq!(
+            run_error(r#"number("abc");"#),
+            "Can't convert 'abc' to a number."
+        );
+    }
+
+    #[test]
+    fn read_line_reads_from_injected_reader() {
+        let input = Cursor::new("first line\r\nsecond line");
+        let mut interpreter = Interpreter::with_input(Box::new(input));
+        let mut read = || super::read_line(&mut interpreter, &[]);
+        assert_eq!(read(), Ok(Literal::String(String::from("first line"))));
+        assert_eq!(read(), Ok(Literal::String(String::from("second line"))));
+        assert_eq!(read(), Ok(Literal::Nil));
+    }
+
+    #[test]
+    fn push_pop_and_length() {
+        let source = r#"
+            var xs = [];
+            var ys = xs;
+            push(xs, 1);
+            push(xs, 2);
+            push(xs, 3);
+            print pop(xs);
+            print length(ys);
+            print ys;
+        "#;
+        assert_eq!(run(source), "3.0\n2.0\n[1.0, 2.0]\n");
+    }
+
+    #[test]
+    fn list_natives_check_their_arguments() {
+        assert_eq!(run_error("pop([]);"), "Can't pop from an empty list.");
+        assert_eq!(
+            run_error("push(\"abc\", 1);"),
+            "First argument to push() must be a list."
+        );
+        assert_eq!(
+            run_error("length(nil);"),
+            "First argument to length() must be a list."
+        );
+    }
+
+    #[test]
+    fn typeof_names_each_type() {
+        let source = r#"
+            fun f() {}
+            class C {}
+            print typeof(1);
+            print typeof("a");
+            print typeof(true);
+            print typeof(nil);
+            print typeof(f);
+            print typeof(clock) == "function";
+            print typeof(C);
+            print typeof(C());
+            print typeof([]);
+            print typeof({});
+        "#;
+        assert_eq!(
+            run(source),
+            "number\nstring\nbool\nnil\nfunction\ntrue\nclass\ninstance\nlist\nmap\n"
+        );
+    }
+
+    #[test]
+    fn echo_prints_and_returns_its_argument() {
+        assert_eq!(run("print echo(5) + 1 == 6;"), "5.0\ntrue\n");
+        assert_eq!(run("var x = echo(\"hi\");"), "hi\n");
+    }
+
+    #[test]
+    fn sleep_zero_returns_nil() {
+        assert_eq!(run("print sleep(0);"), "nil\n");
+    }
+
+    #[test]
+    fn sleep_needs_non_negative_number() {
+        let message = "Argument to sleep() must be a non-negative number.";
+        assert_eq!(run_error("sleep(-1);"), message);
+        assert_eq!(run_error("sleep(\"1\");"), message);
+    }
+
+    #[test]
+    fn sleep_too_long_is_an_error() {
+        let message = "Argument to sleep() is too long.";
+        assert_eq!(run_error("sleep(pow(10, 300));"), message);
+    }
+
+    #[test]
+    fn floor_ceil_round_and_abs() {
+        let source = "print floor(2.7) == 2; print ceil(2.1) == 3; print round(2.5) == 3; print abs(-4) == 4;";
+        assert_eq!(run(source), "true\ntrue\ntrue\ntrue\n");
+        assert_eq!(run("print round(-2.5);"), "-3.0\n");
+    }
+
+    #[test]
+    fn rounding_needs_a_number() {
+        assert_eq!(
+            run_error("floor(\"2\");"),
+            "Argument to floor() must be a number."
+        );
+        assert_eq!(
+            run_error("abs(nil);"),
+            "Argument to abs() must be a number."
+        );
+    }
+
+    #[test]
+    fn sqrt_pow_min_and_max() {
+        let source = "print sqrt(9) == 3; print pow(2, 10) == 1024; print min(3, 5) == 3; print max(3, 5) == 5;";
+        assert_eq!(run(source), "true\ntrue\ntrue\ntrue\n");
+    }
+
+    #[test]
+    fn sqrt_of_negative_is_an_error() {
+        assert_eq!(run_error("sqrt(-4);"), "Can't take the square root of -4.");
+        assert_eq!(
+            run_error("pow(2, \"3\");"),
+            "Arguments to pow() must be numbers."
+        );
+    }
+
+    #[test]
+    fn assert_passes_on_truthy_condition() {
+        assert_eq!(run("print assert(true);"), "nil\n");
+        assert_eq!(run("print assert(1 == 1, \"eq\");"), "nil\n");
+    }
+
+    #[test]
+    fn assert_fails_with_message() {
+        assert_eq!(run_error("assert(false, \"boom\");"), "boom");
+        assert_eq!(run_error("assert(nil);"), "Assertion failed.");
+    }
+}
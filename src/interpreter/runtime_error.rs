@@ -0,0 +1,14 @@
+use crate::scanner::Token;
+
+// An error raised while evaluating, along with the token that caused it.
+#[derive(Debug)]
+pub struct RuntimeError {
+    pub token: Token,
+    pub message: String,
+}
+
+impl RuntimeError {
+    pub fn new(token: Token, message: String) -> Self {
+        RuntimeError { token, message }
+    }
+}
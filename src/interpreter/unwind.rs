@@ -0,0 +1,18 @@
+use super::RuntimeError;
+use crate::scanner::Literal;
+
+// Why the interpreter stopped executing statements in order.
+#[derive(Debug)]
+pub enum Unwind {
+    Error(RuntimeError),
+    Return(Literal), // A return statement, carrying the returned value up to the call.
+    TailCall(Vec<Literal>), // A function returning a call to itself, with the new arguments.
+    Break,           // A break statement, stopping the nearest enclosing loop.
+    Continue,        // A continue statement, skipping the rest of the loop's body.
+}
+
+impl From<RuntimeError> for Unwind {
+    fn from(error: RuntimeError) -> Self {
+        Unwind::Error(error)
+    }
+}
@@ -1,48 +1,220 @@
+mod ast_printer;
+mod ast_stats;
+mod diagnostics;
+mod expression;
+mod interpreter;
+mod parse_cache;
+mod parser;
+mod resolver;
 mod scanner;
+mod statement;
+#[cfg(test)]
+mod testing;
 
-use std::env::args;
+use std::cell::RefCell;
+use std::env::{args, var_os};
 use std::fs::read_to_string;
-use std::io::{stdin, stdout, Write};
+use std::io::{self, stdin, stdout, BufRead, IsTerminal, Write};
+use std::mem;
 use std::process::exit;
+use std::rc::Rc;
 use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
 
-use scanner::{Scanner, Token};
+use ast_printer::AstPrinter;
+use ast_stats::AstStats;
+use diagnostics::Diagnostics;
+use interpreter::{Interpreter, RuntimeError};
+use parse_cache::{ParseCache, Program};
+use parser::Parser;
+use resolver::Resolver;
+use scanner::{NilStyle, ScanError, Scanner, Token};
+use statement::Statement;
 
 /* Avoids passing in self as argument which allows you to
 continue with the tutorial without changing the structure
 of the code too much for error and report function. */
 static HAD_ERROR: AtomicBool = AtomicBool::new(false);
+static HAD_RUNTIME_ERROR: AtomicBool = AtomicBool::new(false);
 
-struct Lox {}
+/* Whether errors are coloured. Only when writing to a terminal or when CLICOLOR_FORCE
+is set to something other than 0, and never with --no-color or when the NO_COLOR
+environment variable is set to anything. */
+static COLOR: AtomicBool = AtomicBool::new(false);
+
+// ANSI styles for the parts of an error.
+const BOLD: &str = "1";
+const RED: &str = "1;31";
+const YELLOW: &str = "1;33";
+
+// Wrap the text in the given ANSI style, if errors are being coloured.
+fn paint(style: &str, text: &str) -> String {
+    styled(COLOR.load(Ordering::Relaxed), style, text)
+}
+
+// Wrap the text in the given ANSI style, or leave it plain without colour.
+fn styled(color: bool, style: &str, text: &str) -> String {
+    if color {
+        format!("\x1b[{}m{}\x1b[0m", style, text)
+    } else {
+        String::from(text)
+    }
+}
+
+/* The stack the interpreter runs with, since the main thread's isn't enough for the
+default call depth. The memory is only reserved, and is used as calls get that deep. */
+const STACK_SIZE: usize = 64 * 1024 * 1024;
+
+struct Lox {
+    scanner: Scanner,         // Kept between runs, along with its '#if' flags.
+    ast: bool,                // Print the syntax tree instead of running it.
+    ast_stats: bool,          // Report on the syntax tree instead of running it.
+    tokens: bool,             // List the scanned tokens instead of running them.
+    tokens_json: bool,        // List the scanned tokens as JSON instead of running them.
+    dump_env: bool,           // Print the globals once a script has run successfully.
+    whitespace: bool,         // List whitespace tokens too, then stop after scanning.
+    bare_expression: bool,    // Let an expression typed at the prompt leave off its ';'.
+    nil_style: NilStyle,      // How nil is spelled in token listings and output.
+    cache: ParseCache,        // Programs that have already been run.
+    history: Vec<String>,     // Everything entered at the prompt, oldest first.
+    interpreter: Interpreter, // Shared by every run, so the prompt remembers earlier lines.
+}
 
 impl Lox {
+    fn new() -> Self {
+        Lox {
+            scanner: Scanner::new(String::new()),
+            ast: false,
+            ast_stats: false,
+            tokens: false,
+            tokens_json: false,
+            dump_env: false,
+            whitespace: false,
+            bare_expression: false,
+            nil_style: NilStyle::Nil,
+            cache: ParseCache::new(),
+            history: Vec::new(),
+            interpreter: Interpreter::new(),
+        }
+    }
+
     fn main(&mut self) {
-        let args: Vec<String> = args().collect();
-        if args.len() > 2 {
-            println!("Usage: jlox [script]");
-            exit(64);
-        } else if args.len() == 2 {
-            self.run_file(args[1].clone());
+        let mut scripts: Vec<String> = Vec::new();
+        let mut args = args().skip(1);
+
+        let no_color = var_os("NO_COLOR").is_some_and(|value| !value.is_empty());
+        let force_color =
+            var_os("CLICOLOR_FORCE").is_some_and(|value| value != "0" && !value.is_empty());
+        let color = (stdout().is_terminal() || force_color) && !no_color;
+        COLOR.store(color, Ordering::Relaxed);
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--define" => match args.next() {
+                    Some(name) => self.scanner.define(name),
+                    None => self.usage(),
+                },
+                "--max-call-depth" => match args.next().and_then(|depth| depth.parse().ok()) {
+                    Some(depth) => self.interpreter.set_max_call_depth(depth),
+                    None => self.usage(),
+                },
+                "--version" => self.version(),
+                "--ast" => self.ast = true,
+                "--ast-stats" => self.ast_stats = true,
+                "--tokens" => self.tokens = true,
+                "--tokens-json" => self.tokens_json = true,
+                "--dump-env" => self.dump_env = true,
+                "--no-color" => COLOR.store(false, Ordering::Relaxed),
+                "--nil-style=nil" => self.set_nil_style(NilStyle::Nil),
+                "--nil-style=null" => self.set_nil_style(NilStyle::Null),
+                "--whitespace" => {
+                    self.whitespace = true;
+                    self.scanner.keep_whitespace();
+                }
+                _ => scripts.push(arg),
+            }
+        }
+
+        if scripts.len() > 1 {
+            self.usage();
+        } else if scripts.len() == 1 {
+            self.run_file(scripts[0].clone());
         } else {
             self.run_prompt();
         }
     }
 
-    // Execute the provided source.
-    fn run_file(&self, path: String) {
-        match read_to_string(path) {
-            Ok(source) => self.run(source),
-            Err(error) => panic!("{}", error),
+    fn set_nil_style(&mut self, nil_style: NilStyle) {
+        self.nil_style = nil_style;
+        self.interpreter.set_nil_style(nil_style);
+    }
+
+    // Print out how to use the command and exit.
+    fn usage(&self) {
+        println!("Usage: jlox [--version] [--ast] [--ast-stats] [--tokens] [--tokens-json] [--whitespace] [--dump-env] [--no-color] [--nil-style={{nil|null}}] [--define NAME]... [--max-call-depth N] [script | -]");
+        exit(64);
+    }
+
+    // Print out the version and how the interpreter was built, then exit.
+    fn version(&self) {
+        let profile = if cfg!(debug_assertions) {
+            "debug"
+        } else {
+            "release"
+        };
+
+        println!(
+            "{} {} ({} build)",
+            env!("CARGO_PKG_NAME"),
+            env!("CARGO_PKG_VERSION"),
+            profile
+        );
+        println!("A tree-walk interpreter for the Lox language.");
+        exit(0);
+    }
+
+    /* Execute the provided source, reading it from stdin when the path is '-'. Errors
+    found before running exit with 65 and errors while running exit with 70, so both
+    can be told apart from a usage error. */
+    fn run_file(&mut self, path: String) {
+        let result = if path == "-" {
+            self.run_reader(&mut stdin().lock(), "<stdin>")
+        } else {
+            read_to_string(&path).map(|source| self.run(source, &path))
         };
+        if let Err(error) = result {
+            panic!("{}", error);
+        }
         if HAD_ERROR.load(Ordering::Relaxed) {
-            exit(64);
+            exit(65);
+        }
+        if HAD_RUNTIME_ERROR.load(Ordering::Relaxed) {
+            exit(70);
+        }
+        if self.dump_env {
+            for (name, value) in self.interpreter.defined_globals() {
+                println!("{} = {}", name, value.display(self.nil_style));
+            }
         }
     }
 
-    // Run an interactive prompt.
+    // Read the whole of the source from the reader before running it.
+    fn run_reader(&mut self, reader: &mut dyn BufRead, source_name: &str) -> io::Result<()> {
+        let mut source = String::new();
+        reader.read_to_string(&mut source)?;
+        self.run(source, source_name);
+        Ok(())
+    }
+
+    /* Run an interactive prompt until the input ends or the user types quit or exit.
+    Lines are collected until their brackets balance, so a function or class can be
+    typed over several lines. A blank line runs whatever has been collected so far. */
     fn run_prompt(&mut self) {
+        self.bare_expression = true;
+        self.interpreter.set_repl(true);
+        let mut buffer = String::new();
         loop {
-            print!("> ");
+            print!("{}", if buffer.is_empty() { "> " } else { "... " });
             /* Stdout is line buffered. Flush is triggered on a new line.
             We need to flush out the currently line manually.*/
             match stdout().flush() {
@@ -53,37 +225,401 @@ impl Lox {
             };
             let mut input = String::new();
             match stdin().read_line(&mut input) {
-                Ok(_) => self.run(input),
+                Ok(0) => break,
+                Ok(_) => {
+                    if buffer.is_empty() && matches!(input.trim(), "quit" | "exit") {
+                        break;
+                    }
+                    if buffer.is_empty() && input.trim_start().starts_with('.') {
+                        self.history.push(String::from(input.trim()));
+                        self.command(input.trim());
+                        HAD_ERROR.store(false, Ordering::Relaxed);
+                        HAD_RUNTIME_ERROR.store(false, Ordering::Relaxed);
+                        continue;
+                    }
+                    let blank = input.trim().is_empty();
+                    buffer.push_str(&input);
+                    if !blank && !is_complete(&buffer) {
+                        continue;
+                    }
+                }
                 Err(error) => {
                     panic!("{}", error);
                 }
             }
+            if !buffer.trim().is_empty() {
+                self.history.push(String::from(buffer.trim_end()));
+            }
+            self.run(mem::take(&mut buffer), "<stdin>");
             HAD_ERROR.store(false, Ordering::Relaxed);
+            HAD_RUNTIME_ERROR.store(false, Ordering::Relaxed);
         }
     }
 
-    // Print out the error and line number.
-    fn run(&self, source: String) {
-        let mut scanner: Scanner = Scanner::new(source);
-        let tokens: Vec<Token> = scanner.scan_tokens();
-        for mut token in tokens {
-            println!("{}", token.to_string());
+    /* Run a prompt command, which starts with a dot so it can't be mistaken for Lox.
+    The rest of the line is the argument, such as the source to list tokens for. */
+    fn command(&mut self, line: &str) {
+        let (name, argument) = match line.find(char::is_whitespace) {
+            Some(index) => (&line[..index], line[index..].trim()),
+            None => (line, ""),
+        };
+
+        match name {
+            ".help" => {
+                println!(".help           Show this list of commands.");
+                println!(".tokens SOURCE  List the tokens scanned from the source.");
+                println!(".ast SOURCE     Print the syntax tree parsed from the source.");
+                println!(".clear          Forget everything defined so far.");
+                println!(".history        List everything entered so far.");
+                println!("quit, exit      Leave the prompt.");
+            }
+            ".tokens" => {
+                let tokens = mem::replace(&mut self.tokens, true);
+                self.run(String::from(argument), "<stdin>");
+                self.tokens = tokens;
+            }
+            ".ast" => {
+                let ast = mem::replace(&mut self.ast, true);
+                self.run(String::from(argument), "<stdin>");
+                self.ast = ast;
+            }
+            ".clear" => {
+                self.interpreter = Interpreter::new();
+                self.interpreter.set_nil_style(self.nil_style);
+                self.interpreter.set_repl(true);
+            }
+            // Lines after the first of a multi-line entry are lined up under it.
+            ".history" => {
+                for (number, entry) in self.history.iter().enumerate() {
+                    println!("{:>4}  {}", number + 1, entry.replace('\n', "\n      "));
+                }
+            }
+            _ => println!(
+                "Unknown command '{}'. Type .help to see the commands.",
+                name
+            ),
         }
     }
 
-    // Print out the error and line number.
-    fn error(line: u32, message: String) {
-        Lox::report(line, String::new(), message);
+    /* Errors are reported against the source name, which is the file path for a script.
+    Running the same source again reuses the program built last time, but only when
+    it's being run rather than listed. */
+    fn run(&mut self, source: String, source_name: &str) {
+        let listing =
+            self.tokens || self.tokens_json || self.whitespace || self.ast || self.ast_stats;
+        if !listing {
+            if let Some(program) = self.cache.get(&source) {
+                for warning in &program.warnings {
+                    Lox::warn(source_name, warning);
+                }
+                self.interpret(&program, source_name);
+                return;
+            }
+        }
+
+        // Shared by every phase before running, so all of their errors are reported together.
+        let diagnostics = Rc::new(RefCell::new(Diagnostics::new(
+            String::from(source_name),
+            &source,
+        )));
+
+        self.scanner.reset(source.clone());
+        let tokens: Vec<Token> = self.scanner.scan_tokens(&mut diagnostics.borrow_mut());
+        let warnings = self.scanner.take_warnings();
+        for warning in &warnings {
+            Lox::warn(source_name, warning);
+        }
+
+        // A JSON array with one token object per line.
+        if self.tokens_json {
+            let tokens: Vec<String> = tokens.iter().map(|token| token.to_json()).collect();
+            println!("[\n  {}\n]", tokens.join(",\n  "));
+            Lox::report(&diagnostics.borrow());
+            return;
+        }
+
+        // The parser doesn't expect whitespace tokens, so they can only be listed.
+        if self.tokens || self.whitespace {
+            for token in &tokens {
+                println!("{}", token.describe(self.nil_style));
+            }
+            Lox::report(&diagnostics.borrow());
+            return;
+        }
+
+        let mut parser: Parser = Parser::new(tokens, Rc::clone(&diagnostics));
+        if self.bare_expression {
+            parser.allow_bare_expression();
+        }
+        let statements: Vec<Box<dyn Statement>> = parser.parse();
+
+        // Stop if there was a syntax error, reporting it along with any scanning errors.
+        if diagnostics.borrow().has_errors() {
+            Lox::report(&diagnostics.borrow());
+            return;
+        }
+
+        if self.ast {
+            let mut printer: AstPrinter = AstPrinter::new();
+            for statement in &statements {
+                println!("{}", printer.print(statement.as_ref()));
+            }
+            return;
+        }
+
+        if self.ast_stats {
+            let mut stats: AstStats = AstStats::new();
+            stats.count(&statements);
+            print!("{}", stats);
+            return;
+        }
+
+        let mut resolver: Resolver = Resolver::new(Rc::clone(&diagnostics));
+        resolver.resolve(&statements);
+
+        // Stop if there was a resolution error.
+        if diagnostics.borrow().has_errors() {
+            Lox::report(&diagnostics.borrow());
+            return;
+        }
+
+        let program = Program::new(statements, resolver.into_locals(), warnings);
+        let program = self.cache.insert(source, program);
+        self.interpret(&program, source_name);
+    }
+
+    // Execute the program, keeping anything it defines for later runs.
+    fn interpret(&mut self, program: &Program, source_name: &str) {
+        self.interpreter.resolve(&program.locals);
+        if let Err(error) = self.interpreter.interpret(&program.statements) {
+            Lox::runtime_error(source_name, error);
+        }
     }
 
-    // Print out the parse error which shows the token location and token lexeme.
-    fn report(line: u32, location: String, message: String) {
-        println!("[line {}] Error {}: {}", line, location, message);
+    // Print out the runtime error and the line it occurred on.
+    fn runtime_error(source_name: &str, error: RuntimeError) {
+        let location = format!("[{}:{}]", source_name, error.token.line);
+        println!("{}\n{}", error.message, paint(BOLD, &location));
+        HAD_RUNTIME_ERROR.store(true, Ordering::Relaxed);
+    }
+
+    // Print out a warning, which unlike an error doesn't change the exit code.
+    fn warn(source_name: &str, warning: &ScanError) {
+        let position = format!("{}:{}:{}:", source_name, warning.line, warning.column);
+        println!(
+            "{} {}: {}",
+            paint(BOLD, &position),
+            paint(YELLOW, "Warning"),
+            warning.message
+        );
+    }
+
+    /* Print out every error found before running, in the order they appear in the
+    source, each followed by the line it's on. */
+    fn report(diagnostics: &Diagnostics) {
+        if !diagnostics.has_errors() {
+            return;
+        }
+
+        for diagnostic in diagnostics.sorted() {
+            let position = format!(
+                "{}:{}:{}:",
+                diagnostics.source_name(),
+                diagnostic.line,
+                diagnostic.column
+            );
+            let mut heading = format!("{} {}", paint(BOLD, &position), paint(RED, "Error"));
+            if !diagnostic.location.is_empty() {
+                heading = format!("{} {}", heading, diagnostic.location);
+            }
+            println!("{}: {}", heading, diagnostic.message);
+
+            if let Some(highlight) = diagnostics.highlight(diagnostic.line, diagnostic.column) {
+                println!("{}", highlight);
+            }
+        }
         HAD_ERROR.store(true, Ordering::Relaxed);
     }
 }
 
+/* Whether every bracket in the source has been closed, ignoring any inside strings
+and comments. A string, block comment or '${' that hasn't been closed yet also means
+there's more to come. */
+fn is_complete(source: &str) -> bool {
+    let chars: Vec<char> = source.chars().collect();
+    let mut brackets: i32 = 0;
+    let mut interpolations: Vec<i32> = Vec::new(); // Braces open in each '${' we're inside.
+    let mut at = 0;
+    while at < chars.len() {
+        let c = chars[at];
+        at += 1;
+        // Where the string or comment starting here ends, if it does.
+        let end = match c {
+            '(' | '[' => {
+                brackets += 1;
+                continue;
+            }
+            ')' | ']' => {
+                brackets -= 1;
+                continue;
+            }
+            '{' => {
+                match interpolations.last_mut() {
+                    Some(depth) => *depth += 1,
+                    None => brackets += 1,
+                }
+                continue;
+            }
+            '}' => match interpolations.last_mut() {
+                // This closes the '${', so carry on with the rest of the string.
+                Some(0) => {
+                    interpolations.pop();
+                    string_end(&chars, at, &mut interpolations)
+                }
+                Some(depth) => {
+                    *depth -= 1;
+                    continue;
+                }
+                None => {
+                    brackets -= 1;
+                    continue;
+                }
+            },
+            '"' if follows(&chars, at, "\"\"") => find(&chars, at + 2, "\"\"\""),
+            '"' => string_end(&chars, at, &mut interpolations),
+            'r' if chars.get(at) == Some(&'"') && !after_name(&chars, at - 1) => {
+                find(&chars, at + 1, "\"")
+            }
+            '/' if follows(&chars, at, "/") => Some(find(&chars, at, "\n").unwrap_or(chars.len())),
+            '/' if follows(&chars, at, "*") => find(&chars, at + 1, "*/"),
+            _ => continue,
+        };
+        match end {
+            Some(end) => at = end,
+            None => return false,
+        }
+    }
+    interpolations.is_empty() && brackets <= 0
+}
+
+/* Where the rest of a string ends, just after either its closing quote or a '${'
+that starts an expression inside it. */
+fn string_end(chars: &[char], mut at: usize, interpolations: &mut Vec<i32>) -> Option<usize> {
+    while at < chars.len() {
+        if chars[at] == '"' {
+            return Some(at + 1);
+        }
+        if follows(chars, at, "${") {
+            interpolations.push(0);
+            return Some(at + 2);
+        }
+        at += 1;
+    }
+    None
+}
+
+// Where the first occurrence of the text at or after the offset ends, if there is one.
+fn find(chars: &[char], mut at: usize, text: &str) -> Option<usize> {
+    while at < chars.len() {
+        if follows(chars, at, text) {
+            return Some(at + text.chars().count());
+        }
+        at += 1;
+    }
+    None
+}
+
+// Whether the characters from the offset on start with the text.
+fn follows(chars: &[char], at: usize, text: &str) -> bool {
+    text.chars()
+        .enumerate()
+        .all(|(offset, c)| chars.get(at + offset) == Some(&c))
+}
+
+// Whether a name runs up to the offset, so an 'r' there is part of it rather than a raw string.
+fn after_name(chars: &[char], at: usize) -> bool {
+    at > 0 && (chars[at - 1].is_alphanumeric() || chars[at - 1] == '_')
+}
+
 fn main() {
-    let mut lox = Lox {};
-    lox.main();
+    let runner = thread::Builder::new().stack_size(STACK_SIZE).spawn(|| {
+        Lox::new().main();
+    });
+    let runner = runner.expect("Failed to start the interpreter.");
+
+    // A panic has already been reported by the thread, so only the exit code is left.
+    if runner.join().is_err() {
+        exit(101);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::{is_complete, styled, Lox, RED};
+    use crate::interpreter::Interpreter;
+    use crate::testing::Output;
+
+    #[test]
+    fn running_same_source_again_hits_cache() {
+        let output = Output::default();
+        let mut lox = Lox::new();
+        lox.interpreter = Interpreter::with_output(Box::new(output.clone()));
+
+        lox.run(String::from("print 1;"), "<test>");
+        assert_eq!(lox.cache.hits(), 0);
+        lox.run(String::from("print 1;"), "<test>");
+        assert_eq!(lox.cache.hits(), 1);
+        assert_eq!(output.text(), "1.0\n1.0\n");
+    }
+
+    #[test]
+    fn listing_modes_skip_cache() {
+        let mut lox = Lox::new();
+        lox.interpreter = Interpreter::with_output(Box::new(Output::default()));
+        lox.run(String::from("1;"), "<test>");
+        lox.ast_stats = true;
+        lox.run(String::from("1;"), "<test>");
+        assert_eq!(lox.cache.hits(), 0);
+    }
+
+    #[test]
+    fn input_is_complete_once_everything_is_closed() {
+        assert!(is_complete("fun f() { return (1); }"));
+        assert!(is_complete("print \"{\"; // ("));
+        assert!(is_complete("print \"${a + \"}\"}\";"));
+        assert!(is_complete("print \"\"\"say \"hi\" \"\"\";"));
+        assert!(is_complete("var r = 1; print r\"(\";"));
+        assert!(!is_complete("fun f() {"));
+        assert!(!is_complete("print \"a"));
+        assert!(!is_complete("/* a comment\n"));
+        assert!(!is_complete("print \"${a + 1"));
+        assert!(!is_complete("print \"${ {\"}\" }"));
+        assert!(!is_complete("print \"\"\"a\"b"));
+    }
+
+    #[test]
+    fn styles_only_when_coloring() {
+        assert_eq!(styled(true, RED, "Error"), "\x1b[1;31mError\x1b[0m");
+        assert_eq!(styled(false, RED, "Error"), "Error");
+    }
+
+    #[test]
+    fn runs_a_program_read_from_a_reader() {
+        let output = Output::default();
+        let mut lox = Lox::new();
+        lox.interpreter = Interpreter::with_output(Box::new(output.clone()));
+        let mut input = Cursor::new("var a = 1;\nprint a + 1;\n");
+        assert!(lox.run_reader(&mut input, "<test>").is_ok());
+        assert_eq!(output.text(), "2.0\n");
+    }
+
+    #[test]
+    fn reader_must_hold_utf8() {
+        let mut lox = Lox::new();
+        lox.interpreter = Interpreter::with_output(Box::new(Output::default()));
+        let mut input = Cursor::new(vec![b'p', 0xff]);
+        assert!(lox.run_reader(&mut input, "<test>").is_err());
+    }
 }
@@ -0,0 +1,134 @@
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::rc::Rc;
+
+use crate::scanner::ScanError;
+use crate::statement::Statement;
+
+// A program that has been scanned, parsed and resolved without errors.
+pub struct Program {
+    pub statements: Vec<Box<dyn Statement>>,
+    pub locals: HashMap<usize, usize>, // The resolver's depth for each local variable use.
+    pub warnings: Vec<ScanError>,      // Shown again each time the program is run.
+}
+
+impl Program {
+    pub fn new(
+        statements: Vec<Box<dyn Statement>>,
+        locals: HashMap<usize, usize>,
+        warnings: Vec<ScanError>,
+    ) -> Self {
+        Program {
+            statements,
+            locals,
+            warnings,
+        }
+    }
+}
+
+// How many programs are kept before the least recently used one is dropped.
+const CAPACITY: usize = 64;
+
+/* Remembers the programs built from each source, so running the same source again
+can skip straight to interpreting it. Entries are keyed by a hash of the source,
+but the source itself is kept so that a hash collision is never mistaken for a hit.
+Only the most recently used programs are kept, so a long session doesn't hold on
+to every line it has ever run. */
+pub struct ParseCache {
+    entries: HashMap<u64, Entry>,
+    clock: u64,  // Counts lookups and inserts, to tell which entry was used last.
+    hits: usize, // How many lookups have found a program.
+}
+
+struct Entry {
+    source: String,
+    program: Rc<Program>,
+    used: u64, // The clock when the entry was last inserted or found.
+}
+
+impl ParseCache {
+    pub fn new() -> Self {
+        ParseCache {
+            entries: HashMap::new(),
+            clock: 0,
+            hits: 0,
+        }
+    }
+
+    // Find the program built from exactly this source, if there is one.
+    pub fn get(&mut self, source: &str) -> Option<Rc<Program>> {
+        self.clock += 1;
+        match self.entries.get_mut(&hash(source)) {
+            Some(entry) if entry.source == source => {
+                entry.used = self.clock;
+                self.hits += 1;
+                Some(Rc::clone(&entry.program))
+            }
+            _ => None,
+        }
+    }
+
+    #[cfg(test)]
+    pub fn hits(&self) -> usize {
+        self.hits
+    }
+
+    /* Remember the program built from the source, replacing any with the same hash.
+    If the cache is full, the entry that was used longest ago makes way for it. */
+    pub fn insert(&mut self, source: String, program: Program) -> Rc<Program> {
+        let key = hash(&source);
+        if !self.entries.contains_key(&key) && self.entries.len() >= CAPACITY {
+            let oldest = self
+                .entries
+                .iter()
+                .min_by_key(|(_, entry)| entry.used)
+                .map(|(key, _)| *key);
+            if let Some(oldest) = oldest {
+                self.entries.remove(&oldest);
+            }
+        }
+
+        self.clock += 1;
+        let program = Rc::new(program);
+        let entry = Entry {
+            source,
+            program: Rc::clone(&program),
+            used: self.clock,
+        };
+        self.entries.insert(key, entry);
+        program
+    }
+}
+
+fn hash(source: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    source.hash(&mut hasher);
+    hasher.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::{ParseCache, Program, CAPACITY};
+
+    fn program() -> Program {
+        Program::new(Vec::new(), HashMap::new(), Vec::new())
+    }
+
+    #[test]
+    fn least_recently_used_program_is_evicted() {
+        let mut cache = ParseCache::new();
+        for line in 0..CAPACITY {
+            cache.insert(format!("print {};", line), program());
+        }
+        assert!(cache.get("print 0;").is_some());
+
+        cache.insert(String::from("print nil;"), program());
+        assert!(cache.get("print 1;").is_none());
+        assert!(cache.get("print 0;").is_some());
+        assert!(cache.get("print 2;").is_some());
+        assert!(cache.get("print nil;").is_some());
+    }
+}
@@ -1,112 +1,1153 @@
-use std::{collections::HashMap};
+use std::cell::RefCell;
+use std::rc::Rc;
 
-use crate::Lox;
+use crate::diagnostics::{Diagnostics, Phase};
+use crate::expression::{
+    Assign, Binary, Call, Expression, Get, Grouping, Index, Interpolation, Lambda, List, Literal,
+    Logical, Map, Set, SetIndex, Super, This, Unary, Variable,
+};
+use crate::scanner::{self, Token, TokenType};
+use crate::statement::{
+    self, Block, Break, Case, Class, Continue, DoWhile, Function, If, Print, Record, Return,
+    Statement, Switch, Var, While,
+};
 
-mod scanner;
-mod expression;
+// Unwinds the parser back to a statement boundary after a syntax error.
+pub struct ParseError;
 
-use scanner::{Token, TokenType};
-use expression::{Expression, Binary};
+// Deep enough for any sensible program while staying well clear of the stack limit.
+const MAX_DEPTH: usize = 256;
+
+// The most parameters a function can declare, and so the most arguments a call can pass.
+const MAX_ARGUMENTS: usize = 255;
 
 pub struct Parser {
     tokens: Vec<Token>,
-    current: u32,
-} 
+    current: usize,
+    depth: usize,
+    max_depth: usize,
+    classes: Vec<bool>,
+    in_initializer: bool,
+    loop_depth: usize,
+    bare_expression: bool,
+    gave_up: bool,
+    diagnostics: Rc<RefCell<Diagnostics>>,
+}
 
 impl Parser {
-    pub fn new(tokens: Vec<Token>) -> Self {
+    pub fn new(tokens: Vec<Token>, diagnostics: Rc<RefCell<Diagnostics>>) -> Self {
+        Parser::with_max_depth(tokens, diagnostics, MAX_DEPTH)
+    }
+
+    pub fn with_max_depth(
+        tokens: Vec<Token>,
+        diagnostics: Rc<RefCell<Diagnostics>>,
+        max_depth: usize,
+    ) -> Self {
         Parser {
             tokens,
             current: 0, // Offset of the current token being parsed.
+            depth: 0,   // How many nested rules we're currently inside.
+            max_depth,
+            classes: Vec::new(), // Whether each class body we're inside has a superclass.
+            in_initializer: false, // Whether we're directly inside an 'init' method.
+            loop_depth: 0,       // How many loops we're inside in the current function.
+            bare_expression: false, // Whether the last expression can leave off its ';'.
+            gave_up: false,      // Whether nesting got too deep to keep parsing.
+            diagnostics,         // Where errors are collected, shared with the other phases.
+        }
+    }
+
+    /* Accept an expression without a semicolon at the end, as typed at the prompt.
+    It's still an expression statement, so its value is echoed just like one with
+    a semicolon would be. */
+    pub fn allow_bare_expression(&mut self) {
+        self.bare_expression = true;
+    }
+
+    // Rule: program -> declaration* EOF ;
+    pub fn parse(&mut self) -> Vec<Box<dyn Statement>> {
+        let mut statements: Vec<Box<dyn Statement>> = Vec::new();
+
+        while !self.is_at_end() {
+            if let Some(statement) = self.declaration() {
+                statements.push(statement);
+            }
+        }
+
+        statements
+    }
+
+    /* Rule: declaration -> classDeclaration | funDeclaration | recordDeclaration
+                          | varDeclaration | statement ;
+    A syntax error skips ahead to the next statement so that we can keep
+    reporting errors in the rest of the program. A 'fun' without a name
+    starts a lambda, which is left to the expression statement. */
+    fn declaration(&mut self) -> Option<Box<dyn Statement>> {
+        let statement = if self.match_tokens(vec![TokenType::CLASS]) {
+            self.class_declaration()
+        } else if self.check(TokenType::FUN) && self.check_next(TokenType::IDENTIFIER) {
+            self.advance();
+            self.function(String::from("function"))
+                .map(|function| Box::new(function) as Box<dyn Statement>)
+        } else if self.match_tokens(vec![TokenType::RECORD]) {
+            self.record_declaration()
+        } else if self.match_tokens(vec![TokenType::VAR]) {
+            self.var_declaration()
+        } else {
+            self.statement()
+        };
+
+        match statement {
+            Ok(statement) => Some(statement),
+            Err(_) => {
+                self.synchronize();
+                None
+            }
+        }
+    }
+
+    // Rule: classDeclaration -> "class" IDENTIFIER ( "<" IDENTIFIER )? "{" function* "}" ;
+    fn class_declaration(&mut self) -> Result<Box<dyn Statement>, ParseError> {
+        let name = self.consume(TokenType::IDENTIFIER, String::from("Expect class name."))?;
+
+        let mut superclass = None;
+        if self.match_tokens(vec![TokenType::LESS]) {
+            let superclass_name = self.consume(
+                TokenType::IDENTIFIER,
+                String::from("Expect superclass name."),
+            )?;
+            if superclass_name.lexeme == name.lexeme {
+                self.error(
+                    &superclass_name,
+                    String::from("A class can't inherit from itself."),
+                );
+            }
+            superclass = Some(Variable::new(superclass_name));
+        }
+
+        self.consume(
+            TokenType::LEFT_BRACE,
+            String::from("Expect '{' before class body."),
+        )?;
+
+        // Pop the class even when a method fails to parse, so 'this' is checked correctly afterwards.
+        self.classes.push(superclass.is_some());
+        let methods = self.methods();
+        self.classes.pop();
+        let methods = methods?;
+
+        self.consume(
+            TokenType::RIGHT_BRACE,
+            String::from("Expect '}' after class body."),
+        )?;
+
+        Ok(Box::new(Class::new(name, superclass, methods)))
+    }
+
+    fn methods(&mut self) -> Result<Vec<Function>, ParseError> {
+        let mut methods: Vec<Function> = Vec::new();
+        while !self.check(TokenType::RIGHT_BRACE) && !self.is_at_end() {
+            methods.push(self.function(String::from("method"))?);
+        }
+
+        Ok(methods)
+    }
+
+    /* Rule: funDeclaration -> "fun" function ;
+             function -> IDENTIFIER "(" parameters? ")" block ;
+    The kind is used in error messages, so that they can be reused for other kinds of functions. */
+    fn function(&mut self, kind: String) -> Result<Function, ParseError> {
+        let name = self.consume(TokenType::IDENTIFIER, format!("Expect {} name.", kind))?;
+        self.consume(
+            TokenType::LEFT_PAREN,
+            format!("Expect '(' after {} name.", kind),
+        )?;
+
+        let params = self.parameters()?;
+
+        // The guard must hold for the arguments of every call.
+        let mut guard = None;
+        if self.match_tokens(vec![TokenType::WHEN]) {
+            guard = Some(self.expression()?);
+        }
+
+        self.consume(
+            TokenType::LEFT_BRACE,
+            format!("Expect '{{' before {} body.", kind),
+        )?;
+        let is_initializer = kind == "method" && name.lexeme == "init";
+        let body = self.function_body(is_initializer)?;
+
+        Ok(Function::new(name, params, guard, body))
+    }
+
+    /* Parse the block of a function, after its opening brace. Functions nested
+    inside an initializer can return values as usual, and a function body isn't
+    inside any loop even if the function itself is. */
+    fn function_body(
+        &mut self,
+        is_initializer: bool,
+    ) -> Result<Vec<Box<dyn Statement>>, ParseError> {
+        let enclosing = (self.in_initializer, self.loop_depth);
+        self.in_initializer = is_initializer;
+        self.loop_depth = 0;
+        let body = self.block();
+        self.in_initializer = enclosing.0;
+        self.loop_depth = enclosing.1;
+
+        body
+    }
+
+    /* Rule: parameters -> IDENTIFIER ( "," IDENTIFIER )* ;
+    Parses the list up to and including the closing parenthesis. */
+    fn parameters(&mut self) -> Result<Vec<Token>, ParseError> {
+        let mut params: Vec<Token> = Vec::new();
+        if !self.check(TokenType::RIGHT_PAREN) {
+            loop {
+                if params.len() >= MAX_ARGUMENTS {
+                    self.error(
+                        self.peek(),
+                        format!("Can't have more than {} parameters.", MAX_ARGUMENTS),
+                    );
+                }
+
+                params.push(self.consume(
+                    TokenType::IDENTIFIER,
+                    String::from("Expect parameter name."),
+                )?);
+
+                if !self.match_tokens(vec![TokenType::COMMA]) {
+                    break;
+                }
+            }
+        }
+        self.consume(
+            TokenType::RIGHT_PAREN,
+            String::from("Expect ')' after parameters."),
+        )?;
+
+        Ok(params)
+    }
+
+    /* Rule: recordDeclaration -> "record" IDENTIFIER "(" parameters? ")" ";" ;
+    The parameters name the record's fields, in the order the constructor takes them. */
+    fn record_declaration(&mut self) -> Result<Box<dyn Statement>, ParseError> {
+        let name = self.consume(TokenType::IDENTIFIER, String::from("Expect record name."))?;
+        self.consume(
+            TokenType::LEFT_PAREN,
+            String::from("Expect '(' after record name."),
+        )?;
+        let fields = self.parameters()?;
+        self.consume(
+            TokenType::SEMICOLON,
+            String::from("Expect ';' after record declaration."),
+        )?;
+
+        Ok(Box::new(Record::new(name, fields)))
+    }
+
+    // Rule: varDeclaration -> "var" variable ";" ;
+    fn var_declaration(&mut self) -> Result<Box<dyn Statement>, ParseError> {
+        let variable = self.variable()?;
+        self.consume(
+            TokenType::SEMICOLON,
+            String::from("Expect ';' after variable declaration."),
+        )?;
+
+        Ok(variable)
+    }
+
+    // Rule: variable -> IDENTIFIER ( "=" expression )? ;
+    fn variable(&mut self) -> Result<Box<dyn Statement>, ParseError> {
+        let name = self.consume(TokenType::IDENTIFIER, String::from("Expect variable name."))?;
+
+        let mut initializer = None;
+        if self.match_tokens(vec![TokenType::EQUAL]) {
+            initializer = Some(self.expression()?);
+        }
+
+        Ok(Box::new(Var::new(name, initializer)))
+    }
+
+    /* Rule: statement -> expressionStatement | forStatement | ifStatement | printStatement
+    | returnStatement | switchStatement | unlessStatement | whileStatement | doWhileStatement
+    | block ; */
+    fn statement(&mut self) -> Result<Box<dyn Statement>, ParseError> {
+        self.nested("Statement nesting too deep.", Parser::any_statement)
+    }
+
+    fn any_statement(&mut self) -> Result<Box<dyn Statement>, ParseError> {
+        if self.match_tokens(vec![TokenType::BREAK]) {
+            return self.break_statement();
+        }
+        if self.match_tokens(vec![TokenType::CONTINUE]) {
+            return self.continue_statement();
+        }
+        if self.match_tokens(vec![TokenType::DO]) {
+            return self.do_while_statement();
+        }
+        if self.match_tokens(vec![TokenType::FOR]) {
+            return self.for_statement();
+        }
+        if self.match_tokens(vec![TokenType::IF]) {
+            return self.if_statement();
+        }
+        if self.match_tokens(vec![TokenType::PRINT]) {
+            return self.print_statement();
+        }
+        if self.match_tokens(vec![TokenType::RETURN]) {
+            return self.return_statement();
+        }
+        if self.match_tokens(vec![TokenType::SWITCH]) {
+            return self.switch_statement();
+        }
+        if self.match_tokens(vec![TokenType::UNLESS]) {
+            return self.unless_statement();
+        }
+        if self.match_tokens(vec![TokenType::WHILE]) {
+            return self.while_statement();
+        }
+        if self.match_tokens(vec![TokenType::LEFT_BRACE]) {
+            return Ok(Box::new(Block::new(self.block()?)));
+        }
+
+        self.expression_statement()
+    }
+
+    // Rule: breakStatement -> "break" ";" ;
+    fn break_statement(&mut self) -> Result<Box<dyn Statement>, ParseError> {
+        // Report the error without unwinding since the parser isn't confused.
+        if self.loop_depth == 0 {
+            self.error(
+                self.previous(),
+                String::from("Must be inside a loop to use 'break'."),
+            );
+        }
+        self.consume(
+            TokenType::SEMICOLON,
+            String::from("Expect ';' after 'break'."),
+        )?;
+
+        Ok(Box::new(Break::new()))
+    }
+
+    // Rule: continueStatement -> "continue" ";" ;
+    fn continue_statement(&mut self) -> Result<Box<dyn Statement>, ParseError> {
+        // Report the error without unwinding since the parser isn't confused.
+        if self.loop_depth == 0 {
+            self.error(
+                self.previous(),
+                String::from("Must be inside a loop to use 'continue'."),
+            );
+        }
+        self.consume(
+            TokenType::SEMICOLON,
+            String::from("Expect ';' after 'continue'."),
+        )?;
+
+        Ok(Box::new(Continue::new()))
+    }
+
+    /* Rule: forStatement -> "for" "(" ( "var" variable ( "," variable )* ";"
+                                     | expression ( "," expression )* ";" | ";" )
+                             expression? ";" ( expression ( "," expression )* )? ")" statement ;
+    There's no For statement, instead the loop is desugared into a while loop. */
+    fn for_statement(&mut self) -> Result<Box<dyn Statement>, ParseError> {
+        self.consume(
+            TokenType::LEFT_PAREN,
+            String::from("Expect '(' after 'for'."),
+        )?;
+
+        let initializers = self.for_initializers()?;
+
+        let mut condition = None;
+        if !self.check(TokenType::SEMICOLON) {
+            condition = Some(self.expression()?);
+        }
+        self.consume(
+            TokenType::SEMICOLON,
+            String::from("Expect ';' after loop condition."),
+        )?;
+
+        let mut increments = Vec::new();
+        if !self.check(TokenType::RIGHT_PAREN) {
+            loop {
+                increments.push(self.expression()?);
+                if !self.match_tokens(vec![TokenType::COMMA]) {
+                    break;
+                }
+            }
+        }
+        self.consume(
+            TokenType::RIGHT_PAREN,
+            String::from("Expect ')' after for clauses."),
+        )?;
+
+        let mut body = self.loop_body()?;
+
+        // Leaving out the condition loops forever.
+        let condition = match condition {
+            Some(condition) => condition,
+            None => Box::new(Literal::new(scanner::Literal::Bool(true))),
+        };
+        // The increments are kept apart from the body, so that they still run after a 'continue'.
+        body = Box::new(While::new(condition, body, increments));
+
+        // The initializers run once, in a scope of their own so the loop variables stay local.
+        if !initializers.is_empty() {
+            let mut statements = initializers;
+            statements.push(body);
+            body = Box::new(Block::new(statements));
+        }
+
+        Ok(body)
+    }
+
+    /* The initializer clause of a for loop up to and including its ';', which can
+    declare or assign several variables separated by commas. */
+    fn for_initializers(&mut self) -> Result<Vec<Box<dyn Statement>>, ParseError> {
+        let mut initializers: Vec<Box<dyn Statement>> = Vec::new();
+        if self.match_tokens(vec![TokenType::SEMICOLON]) {
+            return Ok(initializers);
+        }
+
+        let declaration = self.match_tokens(vec![TokenType::VAR]);
+        loop {
+            if declaration {
+                initializers.push(self.variable()?);
+            } else {
+                let expression = self.expression()?;
+                initializers.push(Box::new(statement::Expression::new(expression)));
+            }
+            if !self.match_tokens(vec![TokenType::COMMA]) {
+                break;
+            }
+        }
+
+        let message = if declaration {
+            "Expect ';' after variable declaration."
+        } else {
+            "Expect ';' after expression."
+        };
+        self.consume(TokenType::SEMICOLON, String::from(message))?;
+        Ok(initializers)
+    }
+
+    /* Rule: ifStatement -> "if" "(" expression ")" statement ( "else" statement )? ;
+    The else is claimed by the nearest if, since we look for it straight after
+    parsing the innermost then branch. */
+    fn if_statement(&mut self) -> Result<Box<dyn Statement>, ParseError> {
+        self.consume(
+            TokenType::LEFT_PAREN,
+            String::from("Expect '(' after 'if'."),
+        )?;
+        let condition = self.expression()?;
+        self.consume(
+            TokenType::RIGHT_PAREN,
+            String::from("Expect ')' after if condition."),
+        )?;
+
+        let then_branch = self.statement()?;
+        let mut else_branch = None;
+        if self.match_tokens(vec![TokenType::ELSE]) {
+            else_branch = Some(self.statement()?);
+        }
+
+        Ok(Box::new(If::new(condition, then_branch, else_branch)))
+    }
+
+    /* Rule: unlessStatement -> "unless" "(" expression ")" statement ( "else" statement )? ;
+    Desugared into an if statement with the condition negated. */
+    fn unless_statement(&mut self) -> Result<Box<dyn Statement>, ParseError> {
+        let keyword = self.previous().clone();
+        self.consume(
+            TokenType::LEFT_PAREN,
+            String::from("Expect '(' after 'unless'."),
+        )?;
+        let condition = self.expression()?;
+        self.consume(
+            TokenType::RIGHT_PAREN,
+            String::from("Expect ')' after unless condition."),
+        )?;
+
+        let not = Token::new(
+            TokenType::BANG,
+            String::from("!"),
+            scanner::Literal::Nil,
+            keyword.line,
+            keyword.column,
+        );
+        let condition = Box::new(Unary::new(not, condition));
+
+        let then_branch = self.statement()?;
+        let mut else_branch = None;
+        if self.match_tokens(vec![TokenType::ELSE]) {
+            else_branch = Some(self.statement()?);
+        }
+
+        Ok(Box::new(If::new(condition, then_branch, else_branch)))
+    }
+
+    // Rule: printStatement -> "print" expression ";" ;
+    fn print_statement(&mut self) -> Result<Box<dyn Statement>, ParseError> {
+        let value = self.expression()?;
+        self.consume(
+            TokenType::SEMICOLON,
+            String::from("Expect ';' after value."),
+        )?;
+
+        Ok(Box::new(Print::new(value)))
+    }
+
+    // Rule: returnStatement -> "return" expression? ";" ;
+    fn return_statement(&mut self) -> Result<Box<dyn Statement>, ParseError> {
+        let keyword = self.previous().clone();
+        let mut value = None;
+        if !self.check(TokenType::SEMICOLON) {
+            // An initializer always returns the new instance.
+            if self.in_initializer {
+                self.error(
+                    &keyword,
+                    String::from("Can't return a value from an initializer."),
+                );
+            }
+            value = Some(self.expression()?);
+        }
+        self.consume(
+            TokenType::SEMICOLON,
+            String::from("Expect ';' after return value."),
+        )?;
+
+        Ok(Box::new(Return::new(value)))
+    }
+
+    /* Rule: switchStatement -> "switch" "(" expression ")" "{" switchCase* "}" ;
+    Rule: switchCase -> ( "case" expression | "default" ) ":" declaration* ;
+    Each case runs until the next one starts, so there's no falling through. */
+    fn switch_statement(&mut self) -> Result<Box<dyn Statement>, ParseError> {
+        self.consume(
+            TokenType::LEFT_PAREN,
+            String::from("Expect '(' after 'switch'."),
+        )?;
+        let subject = self.expression()?;
+        self.consume(
+            TokenType::RIGHT_PAREN,
+            String::from("Expect ')' after switch value."),
+        )?;
+        self.consume(
+            TokenType::LEFT_BRACE,
+            String::from("Expect '{' before switch cases."),
+        )?;
+
+        let mut cases = Vec::new();
+        let mut default = None;
+        while !self.check(TokenType::RIGHT_BRACE) && !self.is_at_end() {
+            if self.match_tokens(vec![TokenType::CASE]) {
+                let value = self.expression()?;
+                self.consume(
+                    TokenType::COLON,
+                    String::from("Expect ':' after case value."),
+                )?;
+                cases.push(Case::new(value, self.case_body()));
+            } else if self.match_tokens(vec![TokenType::DEFAULT]) {
+                let keyword = self.previous().clone();
+                self.consume(
+                    TokenType::COLON,
+                    String::from("Expect ':' after 'default'."),
+                )?;
+                // Report the error without unwinding since the parser isn't confused.
+                if default.is_some() {
+                    self.error(
+                        &keyword,
+                        String::from("A switch can only have one default."),
+                    );
+                }
+                default = Some(self.case_body());
+            } else {
+                return Err(self.error(
+                    self.peek(),
+                    String::from("Expect 'case' or 'default' in switch."),
+                ));
+            }
+        }
+
+        self.consume(
+            TokenType::RIGHT_BRACE,
+            String::from("Expect '}' after switch cases."),
+        )?;
+        Ok(Box::new(Switch::new(subject, cases, default)))
+    }
+
+    // The declarations of a case, up to the start of the next case or the end of the switch.
+    fn case_body(&mut self) -> Vec<Box<dyn Statement>> {
+        let mut statements = Vec::new();
+        while !self.check(TokenType::CASE)
+            && !self.check(TokenType::DEFAULT)
+            && !self.check(TokenType::RIGHT_BRACE)
+            && !self.is_at_end()
+        {
+            if let Some(statement) = self.declaration() {
+                statements.push(statement);
+            }
+        }
+        statements
+    }
+
+    // Rule: doWhileStatement -> "do" statement "while" "(" expression ")" ";" ;
+    fn do_while_statement(&mut self) -> Result<Box<dyn Statement>, ParseError> {
+        let body = self.loop_body()?;
+        self.consume(
+            TokenType::WHILE,
+            String::from("Expect 'while' after do body."),
+        )?;
+        self.consume(
+            TokenType::LEFT_PAREN,
+            String::from("Expect '(' after 'while'."),
+        )?;
+        let condition = self.expression()?;
+        self.consume(
+            TokenType::RIGHT_PAREN,
+            String::from("Expect ')' after condition."),
+        )?;
+        self.consume(
+            TokenType::SEMICOLON,
+            String::from("Expect ';' after do-while condition."),
+        )?;
+
+        Ok(Box::new(DoWhile::new(body, condition)))
+    }
+
+    // Rule: whileStatement -> "while" "(" expression ")" statement ;
+    fn while_statement(&mut self) -> Result<Box<dyn Statement>, ParseError> {
+        self.consume(
+            TokenType::LEFT_PAREN,
+            String::from("Expect '(' after 'while'."),
+        )?;
+        let condition = self.expression()?;
+        self.consume(
+            TokenType::RIGHT_PAREN,
+            String::from("Expect ')' after condition."),
+        )?;
+        let body = self.loop_body()?;
+
+        Ok(Box::new(While::new(condition, body, Vec::new())))
+    }
+
+    // Parse the body of a loop, where 'break' and 'continue' are allowed.
+    fn loop_body(&mut self) -> Result<Box<dyn Statement>, ParseError> {
+        self.loop_depth += 1;
+        let body = self.statement();
+        self.loop_depth -= 1;
+
+        body
+    }
+
+    // Rule: expressionStatement -> expression ";" ;
+    fn expression_statement(&mut self) -> Result<Box<dyn Statement>, ParseError> {
+        let expression = self.expression()?;
+        if self.bare_expression && self.is_at_end() {
+            return Ok(Box::new(statement::Expression::new(expression)));
         }
+        self.consume(
+            TokenType::SEMICOLON,
+            String::from("Expect ';' after expression."),
+        )?;
+
+        Ok(Box::new(statement::Expression::new(expression)))
+    }
+
+    // Rule: block -> "{" declaration* "}" ;
+    fn block(&mut self) -> Result<Vec<Box<dyn Statement>>, ParseError> {
+        self.nested("Statement nesting too deep.", Parser::block_body)
+    }
+
+    fn block_body(&mut self) -> Result<Vec<Box<dyn Statement>>, ParseError> {
+        let mut statements: Vec<Box<dyn Statement>> = Vec::new();
+
+        while !self.check(TokenType::RIGHT_BRACE) && !self.is_at_end() {
+            if let Some(statement) = self.declaration() {
+                statements.push(statement);
+            }
+        }
+
+        self.consume(
+            TokenType::RIGHT_BRACE,
+            String::from("Expect '}' after block."),
+        )?;
+
+        Ok(statements)
+    }
+
+    // Rule: expression -> assignment ;
+    fn expression(&mut self) -> Result<Box<dyn Expression>, ParseError> {
+        self.nested("Expression nesting too deep.", Parser::assignment)
+    }
+
+    /* Rule: assignment -> ( ( call "." )? IDENTIFIER | call "[" expression "]" )
+                           ( "=" | "+=" | "-=" | "*=" | "/=" ) assignment
+                         | logic_or ;
+    The target is parsed as an ordinary expression first, since we don't know it's
+    an assignment until we reach the "=". Only then do we check it's assignable. */
+    fn assignment(&mut self) -> Result<Box<dyn Expression>, ParseError> {
+        let expression = self.or()?;
+
+        if self.match_tokens(vec![
+            TokenType::EQUAL,
+            TokenType::MINUS_EQUAL,
+            TokenType::PLUS_EQUAL,
+            TokenType::SLASH_EQUAL,
+            TokenType::STAR_EQUAL,
+        ]) {
+            let equals = self.previous().clone();
+            let operator = Parser::compound_operator(&equals);
+            let value = self.nested("Expression nesting too deep.", Parser::assignment)?;
+            return Ok(self.assign(expression, equals, operator, value));
+        }
+
+        Ok(expression)
+    }
+
+    // Turn the target into an assignment of the value, if it's something that can be assigned to.
+    fn assign(
+        &mut self,
+        target: Box<dyn Expression>,
+        equals: Token,
+        operator: Option<Token>,
+        value: Box<dyn Expression>,
+    ) -> Box<dyn Expression> {
+        // Reading a variable has no side effects, so x += 1 can simply become x = x + 1.
+        if let Some(variable) = target.as_any().downcast_ref::<Variable>() {
+            let value = match operator {
+                Some(operator) => {
+                    let current = Box::new(Variable::new(variable.name.clone()));
+                    Box::new(Binary::new(current, operator, value))
+                }
+                None => value,
+            };
+            return Box::new(Assign::new(variable.name.clone(), value));
+        }
+
+        /* A property access on the left of '=' becomes a property assignment. The
+        object might have side effects, so a compound operator is applied when the
+        assignment runs rather than by evaluating the object twice. */
+        if target.as_any().is::<Get>() {
+            let get = target.into_any().downcast::<Get>().expect("Checked above.");
+            return Box::new(Set::new(get.object, get.name, operator, value));
+        }
+
+        // Likewise an index on the left of '=' sets that element.
+        if target.as_any().is::<Index>() {
+            let index = target
+                .into_any()
+                .downcast::<Index>()
+                .expect("Checked above.");
+            return Box::new(SetIndex::new(
+                index.object,
+                index.bracket,
+                index.index,
+                operator,
+                value,
+            ));
+        }
+
+        // Report the error without unwinding since the parser isn't confused.
+        self.error(&equals, String::from("Invalid assignment target."));
+        target
+    }
+
+    // The operator a compound assignment such as += or ++ applies, or None for a plain '='.
+    fn compound_operator(equals: &Token) -> Option<Token> {
+        let token_type = match equals.token_type {
+            TokenType::MINUS_EQUAL | TokenType::MINUS_MINUS => TokenType::MINUS,
+            TokenType::PLUS_EQUAL | TokenType::PLUS_PLUS => TokenType::PLUS,
+            TokenType::SLASH_EQUAL => TokenType::SLASH,
+            TokenType::STAR_EQUAL => TokenType::STAR,
+            _ => return None,
+        };
+        // The operator is always the first character, as in the '+' of '+='.
+        let lexeme = equals.lexeme[..1].to_string();
+        Some(Token::new(
+            token_type,
+            lexeme,
+            scanner::Literal::Nil,
+            equals.line,
+            equals.column,
+        ))
+    }
+
+    // Rule: logic_or -> logic_and ( "or" logic_and )* ;
+    fn or(&mut self) -> Result<Box<dyn Expression>, ParseError> {
+        let mut expression = self.and()?;
+
+        while self.match_tokens(vec![TokenType::OR]) {
+            let operator = self.previous().clone();
+            let right = self.and()?;
+            expression = Box::new(Logical::new(expression, operator, right));
+        }
+
+        Ok(expression)
+    }
+
+    // Rule: logic_and -> equality ( "and" equality )* ;
+    fn and(&mut self) -> Result<Box<dyn Expression>, ParseError> {
+        let mut expression = self.equality()?;
+
+        while self.match_tokens(vec![TokenType::AND]) {
+            let operator = self.previous().clone();
+            let right = self.equality()?;
+            expression = Box::new(Logical::new(expression, operator, right));
+        }
+
+        Ok(expression)
     }
 
     // Rule: equality -> comparison ( ( "!=" | "==" ) comparison )* ;.
-    fn equality(&mut self) -> Expression {
-        let mut expression: Expression = self.comparison();
+    fn equality(&mut self) -> Result<Box<dyn Expression>, ParseError> {
+        let mut expression = self.comparison()?;
 
         while self.match_tokens(vec![TokenType::BANG_EQUAL, TokenType::EQUAL_EQUAL]) {
-            let operator = self.previous();
-            let right = self.comparison();
-            expression = Binary.new(expression, operator, right);
+            let operator = self.previous().clone();
+            let right = self.comparison()?;
+            expression = Box::new(Binary::new(expression, operator, right));
         }
 
-        expression
+        Ok(expression)
     }
 
     // Rule: comparison -> term ( ( ">" | ">=" | "<" | "<=" ) term )* ;.
-    fn comparison(&mut self) -> Expression {
-        let mut expression: Expression = self.term();
+    fn comparison(&mut self) -> Result<Box<dyn Expression>, ParseError> {
+        let mut expression = self.term()?;
 
-        while self.match_tokens(vec![TokenType::GREATER, TokenType::GREATER_EQUAL, TokenType::LESS, TokenType::LESS_EQUAL]) {
-            let operator = self.previous();
-            let right = self.term();
-            expression = Binary.new(expression, operator, right);
+        while self.match_tokens(vec![
+            TokenType::GREATER,
+            TokenType::GREATER_EQUAL,
+            TokenType::LESS,
+            TokenType::LESS_EQUAL,
+        ]) {
+            let operator = self.previous().clone();
+            let right = self.term()?;
+            expression = Box::new(Binary::new(expression, operator, right));
         }
 
-        expression
+        Ok(expression)
     }
 
     // Rule: term -> factor ( ( "-" | "+" ) factor )* ;.
-    fn term(&mut self) -> Expression {
-        let mut expression: Expression = self.factor();
+    fn term(&mut self) -> Result<Box<dyn Expression>, ParseError> {
+        let mut expression = self.factor()?;
 
         while self.match_tokens(vec![TokenType::MINUS, TokenType::PLUS]) {
-            let operator = self.previous();
-            let right = self.factor();
-            expression = Binary.new(expression, operator, right);
+            let operator = self.previous().clone();
+            let right = self.factor()?;
+            expression = Box::new(Binary::new(expression, operator, right));
         }
 
-        expression
+        Ok(expression)
     }
 
-    // Rule: factor -> unary ( ( "/" | "*" ) unary )* ;.
-    fn factor(&mut self) -> Expression {
-        let mut expression: Expression = self.unary();
+    // Rule: factor -> power ( ( "/" | "*" | "%" ) power )* ;.
+    fn factor(&mut self) -> Result<Box<dyn Expression>, ParseError> {
+        let mut expression = self.power()?;
 
-        while self.match_tokens(vec![TokenType::SLASH, TokenType::STAR]) {
-            let operator = self.previous();
-            let right = self.unary();
-            expression = Binary.new(expression, operator, right);
+        while self.match_tokens(vec![TokenType::PERCENT, TokenType::SLASH, TokenType::STAR]) {
+            let operator = self.previous().clone();
+            let right = self.power()?;
+            expression = Box::new(Binary::new(expression, operator, right));
         }
 
-        expression
+        Ok(expression)
     }
 
-    // Rule: unary -> ( "!" | "-" ) unary | primary ;
-    fn unary(&mut self) -> Expression {
+    /* Rule: power -> unary ( "**" power )? ;
+    Recursing on the right makes it right associative, so 2 ** 3 ** 2 is 2 ** 9. */
+    fn power(&mut self) -> Result<Box<dyn Expression>, ParseError> {
+        let expression = self.unary()?;
+
+        if self.match_tokens(vec![TokenType::STAR_STAR]) {
+            let operator = self.previous().clone();
+            let right = self.nested("Expression nesting too deep.", Parser::power)?;
+            return Ok(Box::new(Binary::new(expression, operator, right)));
+        }
+
+        Ok(expression)
+    }
+
+    /* Rule: unary -> ( "!" | "-" | "++" | "--" ) unary | call ;
+    Incrementing adds one to the operand and evaluates to the new value, so ++x
+    is parsed just like x += 1. */
+    fn unary(&mut self) -> Result<Box<dyn Expression>, ParseError> {
+        if self.match_tokens(vec![TokenType::MINUS_MINUS, TokenType::PLUS_PLUS]) {
+            let operator = self.previous().clone();
+            let target = self.nested("Expression nesting too deep.", Parser::unary)?;
+            let one = Box::new(Literal::new(scanner::Literal::Number(1.0)));
+            return Ok(self.assign(
+                target,
+                operator.clone(),
+                Parser::compound_operator(&operator),
+                one,
+            ));
+        }
+
         if self.match_tokens(vec![TokenType::BANG, TokenType::MINUS]) {
-            let operator = self.previous();
-            let right = self.unary();
-            return Unary.new(operator, right);
+            let operator = self.previous().clone();
+            let right = self.nested("Expression nesting too deep.", Parser::unary)?;
+            return Ok(Box::new(Unary::new(operator, right)));
+        }
+
+        self.call()
+    }
+
+    // Rule: call -> primary ( "(" arguments? ")" | "." IDENTIFIER | "[" expression "]" )* ;
+    fn call(&mut self) -> Result<Box<dyn Expression>, ParseError> {
+        let mut expression = self.primary()?;
+
+        loop {
+            if self.match_tokens(vec![TokenType::LEFT_PAREN]) {
+                expression = self.finish_call(expression)?;
+            } else if self.match_tokens(vec![TokenType::DOT]) {
+                let name = self.consume(
+                    TokenType::IDENTIFIER,
+                    String::from("Expect property name after '.'."),
+                )?;
+                expression = Box::new(Get::new(expression, name));
+            } else if self.match_tokens(vec![TokenType::LEFT_BRACKET]) {
+                let bracket = self.previous().clone();
+                let index = self.expression()?;
+                self.consume(
+                    TokenType::RIGHT_BRACKET,
+                    String::from("Expect ']' after index."),
+                )?;
+                expression = Box::new(Index::new(expression, bracket, index));
+            } else {
+                break;
+            }
         }
 
-        return self.primary();
+        Ok(expression)
     }
 
-    // Rule: primary -> NUMBER | STRING | "true" | "false" | "nil" | "(" expression ")" ;
-    fn primary(&mut self) -> Expression {
+    // Rule: arguments -> expression ( "," expression )* ;
+    fn finish_call(
+        &mut self,
+        callee: Box<dyn Expression>,
+    ) -> Result<Box<dyn Expression>, ParseError> {
+        let mut arguments: Vec<Box<dyn Expression>> = Vec::new();
+
+        if !self.check(TokenType::RIGHT_PAREN) {
+            loop {
+                // Report the error without unwinding since the parser isn't confused.
+                if arguments.len() >= MAX_ARGUMENTS {
+                    self.error(
+                        self.peek(),
+                        format!("Can't have more than {} arguments.", MAX_ARGUMENTS),
+                    );
+                }
+
+                arguments.push(self.expression()?);
+
+                if !self.match_tokens(vec![TokenType::COMMA]) {
+                    break;
+                }
+            }
+        }
+
+        let paren = self.consume(
+            TokenType::RIGHT_PAREN,
+            String::from("Expect ')' after arguments."),
+        )?;
+
+        Ok(Box::new(Call::new(callee, paren, arguments)))
+    }
+
+    /* Rule: primary -> NUMBER | STRING | "true" | "false" | "nil" | "(" expression ")"
+    | IDENTIFIER | "this" | "super" "." IDENTIFIER | lambda | list | map | interpolation ;
+    A '{' starting a statement is a block, so a map only starts with one inside an expression. */
+    fn primary(&mut self) -> Result<Box<dyn Expression>, ParseError> {
         if self.match_tokens(vec![TokenType::FALSE]) {
-            return Literal.new(false);
+            return Ok(Box::new(Literal::new(scanner::Literal::Bool(false))));
         }
         if self.match_tokens(vec![TokenType::TRUE]) {
-            return Literal.new(true);
+            return Ok(Box::new(Literal::new(scanner::Literal::Bool(true))));
         }
         if self.match_tokens(vec![TokenType::NIL]) {
-            return Literal.new(null);
+            return Ok(Box::new(Literal::new(scanner::Literal::Nil)));
         }
 
         if self.match_tokens(vec![TokenType::NUMBER, TokenType::STRING]) {
-            return Literal.new(self.previous().literal);
+            return Ok(Box::new(Literal::new(self.previous().literal.clone())));
+        }
+
+        if self.match_tokens(vec![TokenType::LEFT_PAREN]) {
+            let expression = self.expression()?;
+            self.consume(
+                TokenType::RIGHT_PAREN,
+                String::from("Expect ')' after expression."),
+            )?;
+            return Ok(Box::new(Grouping::new(expression)));
+        }
+
+        if self.match_tokens(vec![TokenType::SUPER]) {
+            let keyword = self.previous().clone();
+            self.consume(TokenType::DOT, String::from("Expect '.' after 'super'."))?;
+            let method = self.consume(
+                TokenType::IDENTIFIER,
+                String::from("Expect superclass method name."),
+            )?;
+
+            // Report the error without unwinding since the parser isn't confused.
+            match self.classes.last() {
+                None => {
+                    self.error(
+                        &keyword,
+                        String::from("Can't use 'super' outside of a class."),
+                    );
+                }
+                Some(false) => {
+                    self.error(
+                        &keyword,
+                        String::from("Can't use 'super' in a class with no superclass."),
+                    );
+                }
+                Some(true) => {}
+            }
+            return Ok(Box::new(Super::new(keyword, method)));
         }
 
-        if (self.match_tokens(vec![TokenType::LEFT_PAREN])) {
-            let expression = self.expression();
-            self.consume(TokenType::RIGHT_PAREN, "Expect ')' after expression.");
-            return Expression.Grouping(expression);
+        if self.match_tokens(vec![TokenType::THIS]) {
+            let keyword = self.previous().clone();
+            // Report the error without unwinding since the parser isn't confused.
+            if self.classes.is_empty() {
+                self.error(
+                    &keyword,
+                    String::from("Can't use 'this' outside of a class."),
+                );
+            }
+            return Ok(Box::new(This::new(keyword)));
+        }
+
+        if self.match_tokens(vec![TokenType::IDENTIFIER]) {
+            return Ok(Box::new(Variable::new(self.previous().clone())));
+        }
+
+        if self.match_tokens(vec![TokenType::FUN]) {
+            return self.lambda();
         }
+
+        if self.match_tokens(vec![TokenType::LEFT_BRACKET]) {
+            return self.list();
+        }
+
+        if self.match_tokens(vec![TokenType::LEFT_BRACE]) {
+            return self.map();
+        }
+
+        if self.match_tokens(vec![TokenType::STRING_INTERP_START]) {
+            return self.interpolation();
+        }
+
+        Err(self.error(self.peek(), String::from("Expect expression.")))
     }
 
+    // Rule: list -> "[" ( expression ( "," expression )* )? "]" ;
+    fn list(&mut self) -> Result<Box<dyn Expression>, ParseError> {
+        let mut elements = Vec::new();
+        if !self.check(TokenType::RIGHT_BRACKET) {
+            loop {
+                elements.push(self.expression()?);
+                if !self.match_tokens(vec![TokenType::COMMA]) {
+                    break;
+                }
+            }
+        }
+        self.consume(
+            TokenType::RIGHT_BRACKET,
+            String::from("Expect ']' after list elements."),
+        )?;
+
+        Ok(Box::new(List::new(elements)))
+    }
+
+    // Rule: map -> "{" ( expression ":" expression ( "," expression ":" expression )* )? "}" ;
+    fn map(&mut self) -> Result<Box<dyn Expression>, ParseError> {
+        let brace = self.previous().clone();
+        let mut entries = Vec::new();
+        if !self.check(TokenType::RIGHT_BRACE) {
+            loop {
+                let key = self.expression()?;
+                self.consume(TokenType::COLON, String::from("Expect ':' after map key."))?;
+                entries.push((key, self.expression()?));
+                if !self.match_tokens(vec![TokenType::COMMA]) {
+                    break;
+                }
+            }
+        }
+        self.consume(
+            TokenType::RIGHT_BRACE,
+            String::from("Expect '}' after map entries."),
+        )?;
+
+        Ok(Box::new(Map::new(brace, entries)))
+    }
+
+    /* Rule: interpolation -> STRING_INTERP_START expression
+    ( STRING_INTERP_MID expression )* STRING_INTERP_END ; */
+    fn interpolation(&mut self) -> Result<Box<dyn Expression>, ParseError> {
+        let mut parts: Vec<Box<dyn Expression>> =
+            vec![Box::new(Literal::new(self.previous().literal.clone()))];
+        loop {
+            parts.push(self.expression()?);
+            if !self.match_tokens(vec![TokenType::STRING_INTERP_MID]) {
+                break;
+            }
+            parts.push(Box::new(Literal::new(self.previous().literal.clone())));
+        }
+        let end = self.consume(
+            TokenType::STRING_INTERP_END,
+            String::from("Expect '}' after interpolated expression."),
+        )?;
+        parts.push(Box::new(Literal::new(end.literal)));
+
+        Ok(Box::new(Interpolation::new(parts)))
+    }
+
+    // Rule: lambda -> "fun" "(" parameters? ")" block ;
+    fn lambda(&mut self) -> Result<Box<dyn Expression>, ParseError> {
+        self.consume(
+            TokenType::LEFT_PAREN,
+            String::from("Expect '(' after 'fun'."),
+        )?;
+        let params = self.parameters()?;
+        self.consume(
+            TokenType::LEFT_BRACE,
+            String::from("Expect '{' before lambda body."),
+        )?;
+        let body = self.function_body(false)?;
+
+        Ok(Box::new(Lambda::new(params, body)))
+    }
+
+    /* Parse a rule that can recurse back into itself. Statements and expressions
+    share one depth, so deeply nested input of either kind is reported as an error
+    rather than overflowing the stack. The rest of the source is then skipped, as
+    every rule we're nested inside would otherwise report that it wasn't closed. */
+    fn nested<T, F>(&mut self, message: &str, rule: F) -> Result<T, ParseError>
+    where
+        F: FnOnce(&mut Parser) -> Result<T, ParseError>,
+    {
+        if self.depth >= self.max_depth {
+            let error = self.error(self.peek(), String::from(message));
+            self.gave_up = true;
+            self.current = self.tokens.len() - 1;
+            return Err(error);
+        }
+
+        self.depth += 1;
+        let result = rule(self);
+        self.depth -= 1;
+
+        result
+    }
 
     /* Check if the current token has any of the given types.
     If so, return true and consume the token, otherwise return false. */
@@ -121,15 +1162,58 @@ impl Parser {
         false
     }
 
-    fn consume(&mut self, token_type: TokenType, message: String) -> Token {
+    // Consume the current token if it's the expected type, otherwise report an error.
+    fn consume(&mut self, token_type: TokenType, message: String) -> Result<Token, ParseError> {
         if self.check(token_type) {
-            return self.advance();
+            return Ok(self.advance().clone());
+        }
+
+        Err(self.error(self.peek(), message))
+    }
+
+    // Report the error and return it so the caller can decide whether to unwind.
+    fn error(&self, token: &Token, message: String) -> ParseError {
+        if self.gave_up {
+            return ParseError;
         }
 
+        self.diagnostics
+            .borrow_mut()
+            .token_error(Phase::Parse, token, message);
+        ParseError
+    }
+
+    // Discard tokens until we're at the beginning of the next statement.
+    fn synchronize(&mut self) {
+        self.advance();
+
+        while !self.is_at_end() {
+            if self.previous().token_type == TokenType::SEMICOLON {
+                return;
+            }
+
+            match self.peek().token_type {
+                TokenType::CLASS
+                | TokenType::FUN
+                | TokenType::RECORD
+                | TokenType::VAR
+                | TokenType::DO
+                | TokenType::FOR
+                | TokenType::IF
+                | TokenType::WHILE
+                | TokenType::PRINT
+                | TokenType::RETURN
+                | TokenType::SWITCH
+                | TokenType::UNLESS => return,
+                _ => {}
+            }
+
+            self.advance();
+        }
     }
 
     // Return true if the current token is of the given type.
-    fn check(&mut self, token_type: TokenType) -> bool {
+    fn check(&self, token_type: TokenType) -> bool {
         if self.is_at_end() {
             return false;
         }
@@ -137,8 +1221,16 @@ impl Parser {
         self.peek().token_type == token_type
     }
 
+    // Return true if the token after the current one is of the given type.
+    fn check_next(&self, token_type: TokenType) -> bool {
+        match self.tokens.get(self.current + 1) {
+            Some(token) => token.token_type == token_type,
+            None => false,
+        }
+    }
+
     // Consume the current token and return it.
-    fn advance(&mut self) -> Token {
+    fn advance(&mut self) -> &Token {
         if !self.is_at_end() {
             self.current += 1;
         }
@@ -147,17 +1239,199 @@ impl Parser {
     }
 
     // Check if we've run out of tokens to parse.
-    fn is_at_end(&mut self) -> bool {
+    fn is_at_end(&self) -> bool {
         self.peek().token_type == TokenType::EOF
     }
 
     // Return the current token we have yet to consume.
-    fn peek(&mut self) -> Token {
-        self.tokens[self.current]
+    fn peek(&self) -> &Token {
+        &self.tokens[self.current]
     }
 
     // Return the most recent token we have consumed.
-    fn previous(&mut self) -> Token {
-        self.tokens[self.current - 1]
+    fn previous(&self) -> &Token {
+        &self.tokens[self.current - 1]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    use super::Parser;
+    use crate::expression::Binary;
+    use crate::statement::{self, Print, While};
+    use crate::testing::{errors, on_interpreter_stack, parse, run, scan};
+
+    #[test]
+    fn print_statement_wraps_binary_expression() {
+        let statements = parse("print 1 + 2;");
+        assert_eq!(statements.len(), 1);
+        let print = statements[0].as_any().downcast_ref::<Print>();
+        let print = print.expect("Expected a print statement.");
+        assert!(print.expression.as_any().is::<Binary>());
+    }
+
+    #[test]
+    fn print_statement_needs_semicolon() {
+        assert_eq!(errors("print 1"), ["Expect ';' after value."]);
+    }
+
+    #[test]
+    fn deep_nesting_is_a_parse_error() {
+        on_interpreter_stack(|| {
+            let source = format!("print {}1{};", "(".repeat(100_000), ")".repeat(100_000));
+            assert_eq!(errors(&source), ["Expression nesting too deep."]);
+        });
+    }
+
+    #[test]
+    fn deeply_nested_blocks_are_a_parse_error() {
+        on_interpreter_stack(|| {
+            let source = format!("{}{}", "{".repeat(200_000), "}".repeat(200_000));
+            assert_eq!(errors(&source), ["Statement nesting too deep."]);
+        });
+    }
+
+    #[test]
+    fn deeply_nested_ifs_are_a_parse_error() {
+        on_interpreter_stack(|| {
+            // The shared depth runs out partway through a condition.
+            let source = format!("{}print 1;", "if (true) ".repeat(200_000));
+            assert_eq!(errors(&source), ["Expression nesting too deep."]);
+        });
+    }
+
+    #[test]
+    fn max_depth_is_configurable() {
+        let (tokens, diagnostics) = scan("print ((((1))));");
+        let diagnostics = Rc::new(RefCell::new(diagnostics));
+        Parser::with_max_depth(tokens, Rc::clone(&diagnostics), 3).parse();
+        let diagnostics = diagnostics.borrow();
+        let messages: Vec<&str> = diagnostics
+            .sorted()
+            .iter()
+            .map(|diagnostic| diagnostic.message.as_str())
+            .collect();
+        assert_eq!(messages, ["Expression nesting too deep."]);
+    }
+
+    #[test]
+    fn map_entry_needs_colon() {
+        assert_eq!(errors("var m = {1 2};"), ["Expect ':' after map key."]);
+    }
+
+    #[test]
+    fn assignment_target_must_be_variable() {
+        assert_eq!(errors("1 = 2;"), ["Invalid assignment target."]);
+    }
+
+    #[test]
+    fn for_clauses_are_optional() {
+        let statements = parse("for (;;) print 1;");
+        assert!(statements[0].as_any().is::<While>());
+    }
+
+    #[test]
+    fn calls_take_at_most_255_arguments() {
+        let arguments = vec!["1"; 256].join(", ");
+        assert_eq!(
+            errors(&format!("f({});", arguments)),
+            ["Can't have more than 255 arguments."]
+        );
+    }
+
+    #[test]
+    fn this_outside_class() {
+        assert_eq!(
+            errors("print this;"),
+            ["Can't use 'this' outside of a class."]
+        );
+    }
+
+    #[test]
+    fn init_cannot_return_value() {
+        assert_eq!(
+            errors("class Foo { init() { return 1; } }"),
+            ["Can't return a value from an initializer."]
+        );
+    }
+
+    #[test]
+    fn break_outside_loop() {
+        assert_eq!(errors("break;"), ["Must be inside a loop to use 'break'."]);
+        assert_eq!(
+            errors("while (true) { fun f() { break; } }"),
+            ["Must be inside a loop to use 'break'."]
+        );
+    }
+
+    #[test]
+    fn continue_outside_loop() {
+        assert_eq!(
+            errors("continue;"),
+            ["Must be inside a loop to use 'continue'."]
+        );
+    }
+
+    #[test]
+    fn compound_assignment_target_must_be_assignable() {
+        assert_eq!(errors("1 += 2;"), ["Invalid assignment target."]);
+        assert_eq!(errors("var x; x + 1 -= 2;"), ["Invalid assignment target."]);
+    }
+
+    #[test]
+    fn increment_needs_assignable_operand() {
+        assert_eq!(errors("++1;"), ["Invalid assignment target."]);
+        assert_eq!(errors("var x; --(x);"), ["Invalid assignment target."]);
+    }
+
+    #[test]
+    fn trailing_dot_is_reported_once() {
+        assert_eq!(
+            errors("print 123.;"),
+            ["Number literal cannot end with '.'."]
+        );
+    }
+
+    #[test]
+    fn scan_and_parse_errors_are_all_reported_in_order() {
+        assert_eq!(
+            errors("print 1 +;\n@ print 2;\nprint 3 -;"),
+            [
+                "Expect expression.",
+                "Unexpected character.",
+                "Expect expression."
+            ]
+        );
+    }
+
+    #[test]
+    fn parses_a_large_expression() {
+        on_interpreter_stack(|| {
+            let source = format!("print {};", vec!["1"; 10_000].join(" + "));
+            let statements = parse(&source);
+            assert_eq!(statements.len(), 1);
+            assert!(statements[0].as_any().is::<Print>());
+            assert_eq!(run(&source), "10000.0\n");
+        });
+    }
+
+    #[test]
+    fn interpolation_needs_an_expression() {
+        assert_eq!(errors("print \"a${}b\";"), ["Expect expression."]);
+    }
+
+    #[test]
+    fn bare_expression_is_an_expression_statement() {
+        let (tokens, diagnostics) = scan("1 + 2");
+        let diagnostics = Rc::new(RefCell::new(diagnostics));
+        let mut parser = Parser::new(tokens, Rc::clone(&diagnostics));
+        parser.allow_bare_expression();
+        let statements = parser.parse();
+        assert!(!diagnostics.borrow().has_errors());
+        assert!(statements[0].as_any().is::<statement::Expression>());
+        assert_eq!(errors("1 + 2"), ["Expect ';' after expression."]);
     }
 }
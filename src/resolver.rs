@@ -0,0 +1,388 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use crate::diagnostics::{Diagnostics, Phase};
+use crate::expression::{
+    self, Assign, Binary, Call, Expression, Get, Grouping, Index, Interpolation, Lambda, List,
+    Logical, Map, Set, SetIndex, Super, This, Unary, Variable,
+};
+use crate::scanner::Token;
+use crate::statement::{
+    self, Block, Break, Class, Continue, DoWhile, Function, If, Print, Record, Return, Statement,
+    Switch, Var, While,
+};
+
+/* Works out which declaration each variable use refers to before the program runs,
+and records how many scopes out from the use that declaration is. Globals aren't
+tracked, so anything not found in a local scope is assumed global. */
+pub struct Resolver {
+    scopes: Vec<HashMap<String, bool>>, // Whether each name in the scope has finished being defined.
+    locals: HashMap<usize, usize>,      // The depth of each local variable use, by expression id.
+    diagnostics: Rc<RefCell<Diagnostics>>, // Where errors are collected, shared with the other phases.
+}
+
+impl Resolver {
+    pub fn new(diagnostics: Rc<RefCell<Diagnostics>>) -> Self {
+        Resolver {
+            scopes: Vec::new(),
+            locals: HashMap::new(),
+            diagnostics,
+        }
+    }
+
+    // The depths found so far, to be handed to the interpreter.
+    pub fn into_locals(self) -> HashMap<usize, usize> {
+        self.locals
+    }
+
+    pub fn resolve(&mut self, statements: &[Box<dyn Statement>]) {
+        for statement in statements {
+            self.statement(statement.as_ref());
+        }
+    }
+
+    fn statement(&mut self, statement: &dyn Statement) {
+        statement.accept(self)
+    }
+
+    fn expression(&mut self, expression: &dyn Expression) {
+        expression.accept(self)
+    }
+
+    fn begin_scope(&mut self) {
+        self.scopes.push(HashMap::new());
+    }
+
+    fn end_scope(&mut self) {
+        self.scopes.pop();
+    }
+
+    /* Add the name to the innermost scope, but mark it as not ready to be used yet.
+    Redeclaring a local in the same scope is almost certainly a mistake, but
+    globals aren't tracked here so they can still be redeclared. */
+    fn declare(&mut self, name: &Token) {
+        if let Some(scope) = self.scopes.last_mut() {
+            if scope.contains_key(&name.lexeme) {
+                self.diagnostics.borrow_mut().token_error(
+                    Phase::Resolve,
+                    name,
+                    String::from("Already a variable with this name in this scope."),
+                );
+            }
+            scope.insert(name.lexeme.clone(), false);
+        }
+    }
+
+    // Mark the name as fully initialized and available for use.
+    fn define(&mut self, name: &Token) {
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.insert(name.lexeme.clone(), true);
+        }
+    }
+
+    // Record how many scopes out the variable was declared in, if it's a local.
+    fn resolve_local(&mut self, id: usize, name: &Token) {
+        for (depth, scope) in self.scopes.iter().rev().enumerate() {
+            if scope.contains_key(&name.lexeme) {
+                self.locals.insert(id, depth);
+                return;
+            }
+        }
+    }
+
+    // The parameters and body share a scope, matching the environment of a call.
+    fn resolve_function(
+        &mut self,
+        params: &[Token],
+        guard: Option<&dyn Expression>,
+        body: &[Box<dyn Statement>],
+    ) {
+        self.begin_scope();
+        for param in params {
+            self.declare(param);
+            self.define(param);
+        }
+        if let Some(guard) = guard {
+            self.expression(guard);
+        }
+        self.resolve(body);
+        self.end_scope();
+    }
+}
+
+impl expression::Visitor<()> for Resolver {
+    fn visit_assign_expression(&mut self, expression: &Assign) {
+        self.expression(expression.value.as_ref());
+        self.resolve_local(expression.id, &expression.name);
+    }
+
+    fn visit_binary_expression(&mut self, expression: &Binary) {
+        self.expression(expression.left.as_ref());
+        self.expression(expression.right.as_ref());
+    }
+
+    fn visit_call_expression(&mut self, expression: &Call) {
+        self.expression(expression.callee.as_ref());
+        for argument in &expression.arguments {
+            self.expression(argument.as_ref());
+        }
+    }
+
+    // Properties are looked up dynamically, so only the object needs resolving.
+    fn visit_get_expression(&mut self, expression: &Get) {
+        self.expression(expression.object.as_ref());
+    }
+
+    fn visit_grouping_expression(&mut self, expression: &Grouping) {
+        self.expression(expression.expression.as_ref());
+    }
+
+    fn visit_index_expression(&mut self, expression: &Index) {
+        self.expression(expression.object.as_ref());
+        self.expression(expression.index.as_ref());
+    }
+
+    fn visit_interpolation_expression(&mut self, expression: &Interpolation) {
+        for part in &expression.parts {
+            self.expression(part.as_ref());
+        }
+    }
+
+    fn visit_lambda_expression(&mut self, expression: &Lambda) {
+        self.resolve_function(&expression.params, None, &expression.body);
+    }
+
+    fn visit_list_expression(&mut self, expression: &List) {
+        for element in &expression.elements {
+            self.expression(element.as_ref());
+        }
+    }
+
+    fn visit_literal_expression(&mut self, _expression: &expression::Literal) {}
+
+    fn visit_logical_expression(&mut self, expression: &Logical) {
+        self.expression(expression.left.as_ref());
+        self.expression(expression.right.as_ref());
+    }
+
+    fn visit_map_expression(&mut self, expression: &Map) {
+        for (key, value) in &expression.entries {
+            self.expression(key.as_ref());
+            self.expression(value.as_ref());
+        }
+    }
+
+    fn visit_set_expression(&mut self, expression: &Set) {
+        self.expression(expression.value.as_ref());
+        self.expression(expression.object.as_ref());
+    }
+
+    fn visit_set_index_expression(&mut self, expression: &SetIndex) {
+        self.expression(expression.object.as_ref());
+        self.expression(expression.index.as_ref());
+        self.expression(expression.value.as_ref());
+    }
+
+    fn visit_super_expression(&mut self, expression: &Super) {
+        self.resolve_local(expression.id, &expression.keyword);
+    }
+
+    fn visit_this_expression(&mut self, expression: &This) {
+        self.resolve_local(expression.id, &expression.keyword);
+    }
+
+    fn visit_unary_expression(&mut self, expression: &Unary) {
+        self.expression(expression.right.as_ref());
+    }
+
+    fn visit_variable_expression(&mut self, expression: &Variable) {
+        let name = &expression.name;
+        if let Some(scope) = self.scopes.last() {
+            if scope.get(&name.lexeme) == Some(&false) {
+                self.diagnostics.borrow_mut().token_error(
+                    Phase::Resolve,
+                    name,
+                    String::from("Can't read local variable in its own initializer."),
+                );
+            }
+        }
+
+        self.resolve_local(expression.id, name);
+    }
+}
+
+impl statement::Visitor<()> for Resolver {
+    fn visit_block_statement(&mut self, statement: &Block) {
+        self.begin_scope();
+        self.resolve(&statement.statements);
+        self.end_scope();
+    }
+
+    fn visit_break_statement(&mut self, _statement: &Break) {}
+
+    /* Methods are nested inside a scope binding 'this', which in turn is inside
+    one binding 'super' when there's a superclass, matching how the interpreter
+    builds their closures. */
+    fn visit_class_statement(&mut self, statement: &Class) {
+        self.declare(&statement.name);
+        self.define(&statement.name);
+
+        if let Some(superclass) = &statement.superclass {
+            self.expression(superclass);
+            self.begin_scope();
+            if let Some(scope) = self.scopes.last_mut() {
+                scope.insert(String::from("super"), true);
+            }
+        }
+
+        self.begin_scope();
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.insert(String::from("this"), true);
+        }
+        for method in &statement.methods {
+            self.resolve_function(&method.params, method.guard.as_deref(), &method.body);
+        }
+        self.end_scope();
+
+        if statement.superclass.is_some() {
+            self.end_scope();
+        }
+    }
+
+    fn visit_continue_statement(&mut self, _statement: &Continue) {}
+
+    fn visit_do_while_statement(&mut self, statement: &DoWhile) {
+        self.statement(statement.body.as_ref());
+        self.expression(statement.condition.as_ref());
+    }
+
+    fn visit_expression_statement(&mut self, statement: &statement::Expression) {
+        self.expression(statement.expression.as_ref());
+    }
+
+    // The name is defined before the body so that the function can call itself.
+    fn visit_function_statement(&mut self, statement: &Function) {
+        self.declare(&statement.name);
+        self.define(&statement.name);
+        self.resolve_function(
+            &statement.params,
+            statement.guard.as_deref(),
+            &statement.body,
+        );
+    }
+
+    fn visit_if_statement(&mut self, statement: &If) {
+        self.expression(statement.condition.as_ref());
+        self.statement(statement.then_branch.as_ref());
+        if let Some(else_branch) = &statement.else_branch {
+            self.statement(else_branch.as_ref());
+        }
+    }
+
+    fn visit_print_statement(&mut self, statement: &Print) {
+        self.expression(statement.expression.as_ref());
+    }
+
+    fn visit_record_statement(&mut self, statement: &Record) {
+        self.declare(&statement.name);
+        self.define(&statement.name);
+    }
+
+    fn visit_return_statement(&mut self, statement: &Return) {
+        if let Some(value) = &statement.value {
+            self.expression(value.as_ref());
+        }
+    }
+
+    // Each case body is its own block.
+    fn visit_switch_statement(&mut self, statement: &Switch) {
+        self.expression(statement.subject.as_ref());
+        for case in &statement.cases {
+            self.expression(case.value.as_ref());
+            self.begin_scope();
+            self.resolve(&case.body);
+            self.end_scope();
+        }
+        if let Some(default) = &statement.default {
+            self.begin_scope();
+            self.resolve(default);
+            self.end_scope();
+        }
+    }
+
+    fn visit_var_statement(&mut self, statement: &Var) {
+        self.declare(&statement.name);
+        if let Some(initializer) = &statement.initializer {
+            self.expression(initializer.as_ref());
+        }
+        self.define(&statement.name);
+    }
+
+    fn visit_while_statement(&mut self, statement: &While) {
+        self.expression(statement.condition.as_ref());
+        self.statement(statement.body.as_ref());
+        for increment in &statement.increments {
+            self.expression(increment.as_ref());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::testing::{errors, run};
+
+    #[test]
+    fn closure_binding_is_stable() {
+        let source = r#"
+            var a = "global";
+            {
+                fun showA() { print a; }
+                showA();
+                var a = "block";
+                showA();
+            }
+        "#;
+        assert_eq!(run(source), "global\nglobal\n");
+    }
+
+    #[test]
+    fn closures_made_in_a_loop_keep_their_bindings() {
+        let source = r#"
+            var closures = [];
+            for (var i = 0; i < 3; i = i + 1) {
+                var j = i;
+                push(closures, fun () { return j; });
+            }
+            var i = 100;
+            for (var k = 0; k < 3; k = k + 1) print closures[k]();
+        "#;
+        assert_eq!(run(source), "0.0\n1.0\n2.0\n");
+    }
+
+    #[test]
+    fn local_cannot_read_itself_in_initializer() {
+        assert_eq!(
+            errors("{ var a = 1; { var a = a; } }"),
+            ["Can't read local variable in its own initializer."]
+        );
+    }
+
+    #[test]
+    fn global_can_shadow_itself() {
+        assert_eq!(run("var a = 1; var a = a + 1; print a;"), "2.0\n");
+    }
+
+    #[test]
+    fn duplicate_local_declaration() {
+        assert_eq!(
+            errors("{ var a; var a; }"),
+            ["Already a variable with this name in this scope."]
+        );
+    }
+
+    #[test]
+    fn same_name_in_separate_scopes() {
+        assert!(errors("{ var a; } { var a; } var b; var b;").is_empty());
+    }
+}
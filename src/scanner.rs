@@ -1,26 +1,60 @@
-use std::{collections::HashMap};
+use std::collections::{HashMap, HashSet, VecDeque};
 
-use crate::Lox;
+use crate::diagnostics::{Diagnostics, Phase};
 
+mod scan_error;
 mod token;
 
-pub use token::{Token, TokenType};
-use token::Literal;
+pub use scan_error::ScanError;
+pub use token::{Literal, LiteralKey, NilStyle, Token, TokenType};
+
+// Far longer than any real name or number, but short enough to stop runaway input.
+const MAX_IDENTIFIER_LENGTH: usize = 1024;
+const MAX_NUMBER_LENGTH: usize = 1024;
+
+// An '#if' directive that hasn't been closed by '#endif' yet.
+struct Conditional {
+    taken: bool, // Whether the current branch's code is included.
+    in_else: bool,
+}
 
 pub struct Scanner {
-    source: String,
-    tokens: Vec<Token>,
+    source: Vec<char>, // Indexed by character, so multi-byte characters take one slot.
+    scanned: VecDeque<Result<Token, ScanError>>, // Scanned but not yet handed out.
+    finished: bool,    // Whether the EOF token has been scanned.
     start: usize,
     current: usize,
     line: u32,
+    line_start: usize,
+    column: u32,
     keywords: HashMap<String, TokenType>,
+    defines: HashSet<String>,
+    conditionals: Vec<Conditional>,
+    interpolations: Vec<u32>,
+    warnings: Vec<ScanError>,
+    whitespace: bool,
+    max_identifier_length: usize,
+    max_number_length: usize,
 }
 
 impl Scanner {
     pub fn new(source: String) -> Self {
+        Scanner::with_max_lengths(source, MAX_IDENTIFIER_LENGTH, MAX_NUMBER_LENGTH)
+    }
+
+    pub fn with_max_lengths(
+        source: String,
+        max_identifier_length: usize,
+        max_number_length: usize,
+    ) -> Self {
         let mut reserved: HashMap<String, TokenType> = HashMap::new();
         reserved.insert(String::from("and"), TokenType::AND);
+        reserved.insert(String::from("break"), TokenType::BREAK);
+        reserved.insert(String::from("case"), TokenType::CASE);
         reserved.insert(String::from("class"), TokenType::CLASS);
+        reserved.insert(String::from("continue"), TokenType::CONTINUE);
+        reserved.insert(String::from("default"), TokenType::DEFAULT);
+        reserved.insert(String::from("do"), TokenType::DO);
         reserved.insert(String::from("else"), TokenType::ELSE);
         reserved.insert(String::from("false"), TokenType::FALSE);
         reserved.insert(String::from("for"), TokenType::FOR);
@@ -29,57 +63,182 @@ impl Scanner {
         reserved.insert(String::from("nil"), TokenType::NIL);
         reserved.insert(String::from("or"), TokenType::OR);
         reserved.insert(String::from("print"), TokenType::PRINT);
+        reserved.insert(String::from("record"), TokenType::RECORD);
         reserved.insert(String::from("return"), TokenType::RETURN);
         reserved.insert(String::from("super"), TokenType::SUPER);
+        reserved.insert(String::from("switch"), TokenType::SWITCH);
         reserved.insert(String::from("this"), TokenType::THIS);
         reserved.insert(String::from("true"), TokenType::TRUE);
+        reserved.insert(String::from("unless"), TokenType::UNLESS);
         reserved.insert(String::from("var"), TokenType::VAR);
+        reserved.insert(String::from("when"), TokenType::WHEN);
         reserved.insert(String::from("while"), TokenType::WHILE);
 
         Scanner {
-            source,
-            tokens: Vec::new(),
-            start: 0, // Offset of the first character of the lexeme being scanned.
-            current: 0, // Offset of the current character being scanned.
-            line: 1, // Track the line of the current character is on.
+            source: source.chars().collect(),
+            scanned: VecDeque::new(),
+            finished: false,
+            start: 0,      // Offset of the first character of the lexeme being scanned.
+            current: 0,    // Offset of the current character being scanned.
+            line: 1,       // Track the line of the current character is on.
+            line_start: 0, // Offset of the first character of the current line.
+            column: 1,     // Column of the first character of the lexeme being scanned.
             keywords: reserved,
+            defines: HashSet::new(), // Flags that '#if' directives check for.
+            conditionals: Vec::new(), // Stack of the '#if' directives we're inside.
+            interpolations: Vec::new(), // Braces open in each '${' we're inside.
+            warnings: Vec::new(),    // Likely mistakes that still scan fine.
+            whitespace: false,       // Emit tokens for whitespace instead of skipping it.
+            max_identifier_length,   // The most characters an identifier can have.
+            max_number_length,       // The most characters a number literal can have.
         }
     }
 
-    // Work through the source code adding tokens until you run out of characters.
-    pub fn scan_tokens(&mut self) -> Vec<Token> {
-        while !self.is_at_end() {
+    /* Start again on a new source, so the prompt can reuse one scanner for every line.
+    The keywords, the defined flags and whether whitespace is kept all carry over. */
+    pub fn reset(&mut self, source: String) {
+        self.source = source.chars().collect();
+        self.scanned.clear();
+        self.finished = false;
+        self.start = 0;
+        self.current = 0;
+        self.line = 1;
+        self.line_start = 0;
+        self.column = 1;
+        self.conditionals.clear();
+        self.interpolations.clear();
+        self.warnings.clear();
+    }
+
+    // Hand over the warnings found so far, which unlike errors don't stop the program.
+    pub fn take_warnings(&mut self) -> Vec<ScanError> {
+        std::mem::take(&mut self.warnings)
+    }
+
+    // Define a flag so that '#if' directives checking for it are included.
+    pub fn define(&mut self, name: String) {
+        self.defines.insert(name);
+    }
+
+    /* Emit NEWLINE and WHITESPACE tokens rather than skipping them, so that tools
+    like a formatter can see how the source was laid out. */
+    pub fn keep_whitespace(&mut self) {
+        self.whitespace = true;
+    }
+
+    /* Scan the whole source at once, recording any errors with the diagnostics
+    rather than handing them back with the tokens. */
+    pub fn scan_tokens(&mut self, diagnostics: &mut Diagnostics) -> Vec<Token> {
+        let results: Vec<Result<Token, ScanError>> = self.by_ref().collect();
+
+        let mut tokens = Vec::new();
+        for result in results {
+            match result {
+                Ok(token) => tokens.push(token),
+                Err(error) => {
+                    diagnostics.error(Phase::Scan, error.line, error.column, error.message)
+                }
+            }
+        }
+        tokens
+    }
+
+    // Scan the next lexeme, or finish with the EOF token once the source runs out.
+    fn scan_next(&mut self) {
+        if !self.is_at_end() {
             // Currently at the start of the next lexeme.
             self.start = self.current;
+            self.column = (self.start - self.line_start + 1) as u32;
             self.scan_token();
+            return;
+        }
+
+        if !self.conditionals.is_empty() {
+            self.error(String::from("Unterminated '#if' directive."));
         }
 
         // Add EOF token at the end to make our parser cleaner.
-        self.tokens.push(Token::new(
+        self.scanned.push_back(Ok(Token::new(
             TokenType::EOF,
             String::new(),
             Literal::Nil,
             self.line,
-        ));
-
-        self.tokens.clone()
+            (self.current - self.line_start + 1) as u32,
+        )));
+        self.finished = true;
     }
 
     // Try to match a lexeme to create a new token so that it can be added to tokens.
     pub fn scan_token(&mut self) {
         let c = self.advance();
 
+        /* Code excluded by a directive is skipped, but we still need to count
+        lines and look for the directive that ends the exclusion. */
+        if !self.is_active() && c != '\n' && c != '#' {
+            return;
+        }
+
         match c {
             '(' => self.add_token(TokenType::LEFT_PAREN),
             ')' => self.add_token(TokenType::RIGHT_PAREN),
-            '{' => self.add_token(TokenType::LEFT_BRACE),
-            '}' => self.add_token(TokenType::RIGHT_BRACE),
+            '{' => {
+                if let Some(depth) = self.interpolations.last_mut() {
+                    *depth += 1;
+                }
+                self.add_token(TokenType::LEFT_BRACE);
+            }
+            '}' => match self.interpolations.last_mut() {
+                // This closes the '${', so carry on with the rest of the string.
+                Some(0) => {
+                    self.interpolations.pop();
+                    self.string(true);
+                }
+                Some(depth) => {
+                    *depth -= 1;
+                    self.add_token(TokenType::RIGHT_BRACE);
+                }
+                None => self.add_token(TokenType::RIGHT_BRACE),
+            },
+            '[' => self.add_token(TokenType::LEFT_BRACKET),
+            ']' => self.add_token(TokenType::RIGHT_BRACKET),
+            ':' => self.add_token(TokenType::COLON),
             ',' => self.add_token(TokenType::COMMA),
             '.' => self.add_token(TokenType::DOT),
-            '-' => self.add_token(TokenType::MINUS),
-            '+' => self.add_token(TokenType::PLUS),
+            '-' => {
+                let token_type: TokenType = if self.match_token('-') {
+                    TokenType::MINUS_MINUS
+                } else if self.match_token('=') {
+                    TokenType::MINUS_EQUAL
+                } else {
+                    TokenType::MINUS
+                };
+
+                self.add_token(token_type);
+            }
+            '%' => self.add_token(TokenType::PERCENT),
+            '+' => {
+                let token_type: TokenType = if self.match_token('+') {
+                    TokenType::PLUS_PLUS
+                } else if self.match_token('=') {
+                    TokenType::PLUS_EQUAL
+                } else {
+                    TokenType::PLUS
+                };
+
+                self.add_token(token_type);
+            }
             ';' => self.add_token(TokenType::SEMICOLON),
-            '*' => self.add_token(TokenType::STAR),
+            '*' => {
+                let token_type: TokenType = if self.match_token('*') {
+                    TokenType::STAR_STAR
+                } else if self.match_token('=') {
+                    TokenType::STAR_EQUAL
+                } else {
+                    TokenType::STAR
+                };
+
+                self.add_token(token_type);
+            }
             '!' => {
                 let token_type: TokenType = match self.match_token('=') {
                     true => TokenType::BANG_EQUAL,
@@ -118,6 +277,7 @@ impl Scanner {
                     while self.peek() != '\n' && !self.is_at_end() {
                         self.advance();
                     }
+                    self.line_directive();
                 } else if self.match_token('*') {
                     // A multi comment goes until '*/'.
                     while !self.is_at_end() {
@@ -128,31 +288,146 @@ impl Scanner {
                             break;
                         }
 
-                        if self.match_token('\n') {
-                            self.line += 1;
+                        /* Only the '\n' of a '\r\n' pair counts, and checking what was just
+                        consumed means back to back newlines are all counted. */
+                        if self.advance() == '\n' {
+                            self.newline();
                         }
-
-                        self.advance();
                     }
+                } else if self.match_token('=') {
+                    self.add_token(TokenType::SLASH_EQUAL);
                 } else {
                     self.add_token(TokenType::SLASH);
                 }
             }
-            ' ' | '\r' | '\t' => {} // Ignore whitespace. Do nothing.
-            '\n' => self.line += 1,
-            '"' => self.string(),
+            ' ' | '\r' | '\t' => self.whitespace(),
+            '\n' => {
+                if self.whitespace {
+                    self.add_token(TokenType::NEWLINE);
+                }
+                self.newline();
+            }
+            '"' => {
+                if self.peek() == '"' && self.peek_next() == '"' {
+                    // The rest of the opening """.
+                    self.advance();
+                    self.advance();
+                    self.triple_quoted_string();
+                } else {
+                    self.string(false);
+                }
+            }
+            '#' => self.directive(),
+            'r' if self.peek() == '"' => {
+                // The opening ".
+                self.advance();
+                self.raw_string();
+            }
             _ => {
                 if self.is_digit(c) {
                     self.number();
                 } else if self.is_alpha(c) {
                     self.identifier();
                 } else {
-                    Lox::error(self.line, String::from("Unexpected character."));
+                    self.error(String::from("Unexpected character."));
+                }
+            }
+        }
+    }
+
+    // Ignore whitespace, unless we've been asked to keep it as a single token per run.
+    fn whitespace(&mut self) {
+        if !self.whitespace {
+            return;
+        }
+
+        while self.peek() == ' ' || self.peek() == '\r' || self.peek() == '\t' {
+            self.advance();
+        }
+
+        self.add_token(TokenType::WHITESPACE);
+    }
+
+    // Handle an '#if FLAG', '#else' or '#endif' conditional compilation directive.
+    fn directive(&mut self) {
+        let name = self.directive_word();
+
+        match name.as_str() {
+            "if" => {
+                // Skip the whitespace between the directive and the flag.
+                while self.peek() == ' ' || self.peek() == '\t' {
+                    self.advance();
+                }
+
+                let flag = self.directive_word();
+                if flag.is_empty() {
+                    self.error(String::from("Expect flag name after '#if'."));
+                    return;
+                }
+
+                let taken = self.defines.contains(&flag);
+                self.conditionals.push(Conditional {
+                    taken,
+                    in_else: false,
+                });
+            }
+            "else" => match self.conditionals.last_mut() {
+                Some(conditional) if !conditional.in_else => {
+                    conditional.taken = !conditional.taken;
+                    conditional.in_else = true;
+                }
+                _ => self.error(String::from("'#else' without matching '#if'.")),
+            },
+            "endif" => {
+                if self.conditionals.pop().is_none() {
+                    self.error(String::from("'#endif' without matching '#if'."));
+                }
+            }
+            _ => {
+                // Anything can appear in excluded code, so only complain about included code.
+                if self.is_active() {
+                    self.error(String::from("Unknown directive."));
                 }
             }
         }
     }
 
+    /* A '//#line N' comment says the next line is line N of the original source, so
+    errors in generated code point back to where it came from. Anything that doesn't
+    quite match is left as an ordinary comment. */
+    fn line_directive(&mut self) {
+        let comment: String = self.source[self.start + 2..self.current].iter().collect();
+        let number = match comment.strip_prefix("#line ") {
+            Some(number) => number.trim(),
+            None => return,
+        };
+        if let Ok(line) = number.parse::<u32>() {
+            if line > 0 {
+                // The newline ending this comment moves on to the given line.
+                self.line = line - 1;
+            }
+        }
+    }
+
+    // Consume the directive name or flag following the current character.
+    fn directive_word(&mut self) -> String {
+        let mut word = String::new();
+        let mut c = self.peek();
+
+        while self.is_alpha_numeric(c) {
+            word.push(self.advance());
+            c = self.peek();
+        }
+
+        word
+    }
+
+    // Check if the code being scanned is included by every enclosing '#if'.
+    fn is_active(&self) -> bool {
+        self.conditionals
+            .iter()
+            .all(|conditional| conditional.taken)
+    }
 
     // Consume the entire identifier literal.
     fn identifier(&mut self) {
@@ -164,20 +439,33 @@ impl Scanner {
             c = self.peek();
         }
 
-        let source = self.source.clone();
-
-        if let Some(text) = source.get(self.start..self.current) {
-            let keywords = self.keywords.clone();
-            let mut token_type = TokenType::IDENTIFIER;
+        // The whole identifier is still consumed so the rest isn't scanned as more tokens.
+        if self.current - self.start > self.max_identifier_length {
+            self.error(String::from("Identifier is too long."));
+            return;
+        }
 
-            if let Some(value) = keywords.get_key_value(text) {
-                token_type = value.1.clone();
-            }
+        // The lexeme is its own string, so looking it up only borrows the keywords.
+        let text = self.lexeme();
+        let token_type = match self.keywords.get(&text) {
+            Some(token_type) => token_type.clone(),
+            None => TokenType::IDENTIFIER,
+        };
 
-            self.add_token(token_type);
-        } else {
-            panic!("Failed to get identifier!");
+        // Something like 'If' or 'CLASS' was probably meant to be the keyword.
+        let lowercase = text.to_lowercase();
+        if token_type == TokenType::IDENTIFIER && self.keywords.contains_key(&lowercase) {
+            self.warnings.push(ScanError::new(
+                self.line,
+                self.column,
+                format!(
+                    "'{}' is an identifier, not the keyword '{}'.",
+                    text, lowercase
+                ),
+            ));
         }
+
+        self.add_token(token_type);
     }
 
     // Consume the number literal, which can be an natural or decimal number.
@@ -208,126 +496,224 @@ impl Scanner {
                 self.advance();
                 c = self.peek();
             }
+        } else if self.peek() == '.' && !self.is_alpha(c) {
+            /* A dot followed by a name is a method call on the number, but anything
+            else is a float missing its fraction. The dot is consumed and the number
+            still added, so the parser doesn't report the same mistake again. */
+            self.advance();
+            self.error(String::from("Number literal cannot end with '.'."));
         }
 
-        let source = self.source.clone();
+        if self.current - self.start > self.max_number_length {
+            self.error(String::from("Number literal is too long."));
+            return;
+        }
 
-        // Trim the surrounding quotes.
-        if let Some(value) = source.get(self.start..self.current) {
-            if let Ok(number) = value.parse() {
-                self.add_token_complete(TokenType::NUMBER, Literal::Number(number));
-            } else {
-                panic!("Failed to convert to number!")
-            }
-        } else {
-            panic!("Failed to get substring from source!");
+        match self.lexeme().parse() {
+            Ok(number) => self.add_token_complete(TokenType::NUMBER, Literal::Number(number)),
+            Err(_) => self.error(String::from("Invalid number.")),
         }
     }
 
-    // Consume the entire string literal.
-    fn string(&mut self) {
+    /* Consume the entire string literal. A backslash right before a newline
+    continues the string on the next line, leaving both out of the value.
+    A '${' stops the string so the expression inside can be scanned as tokens,
+    and the '}' closing it comes back here to continue where it left off. */
+    fn string(&mut self, continued: bool) {
+        let mut value = String::new();
+
         while self.peek() != '"' && !self.is_at_end() {
-            if self.peek() == '\n' {
-                self.line += 1;
-            } else {
+            if self.peek() == '$' && self.peek_next() == '{' {
+                self.advance();
+                self.advance();
+                self.interpolations.push(0);
+
+                let token_type = if continued {
+                    TokenType::STRING_INTERP_MID
+                } else {
+                    TokenType::STRING_INTERP_START
+                };
+                self.add_token_complete(token_type, Literal::String(value));
+                return;
+            }
+
+            let c = self.advance();
+
+            // A '\r\n' line ending continues the string just like a '\n' does.
+            if c == '\\' && self.peek() == '\r' && self.peek_next() == '\n' {
+                self.advance();
+            }
+            if c == '\\' && self.peek() == '\n' {
                 self.advance();
+                self.newline();
+                continue;
+            }
+
+            if c == '\\' && self.is_at_end() {
+                self.error(String::from("Unterminated line continuation."));
+                return;
+            }
+
+            if c == '\n' {
+                self.newline();
             }
+            value.push(c);
         }
 
         if self.is_at_end() {
-            Lox::error(self.line, String::from("Unterminated string."));
+            self.error(String::from("Unterminated string."));
             return;
         }
 
         // The closing ".
         self.advance();
 
-        /* Need to create copies of required vars to avoid mutable_borrow_reservation_conflict.
-        This avoids borrowing self as immutable at source.get and self.add_token_complete in
-        two places. */
-        let source = self.source.clone();
-        let start = self.start + 1;
-        let end = self.current - 1;
+        let token_type = if continued {
+            TokenType::STRING_INTERP_END
+        } else {
+            TokenType::STRING
+        };
+        self.add_token_complete(token_type, Literal::String(value));
+    }
+
+    // Consume a string literal between """, where newlines are kept just as they're written.
+    fn triple_quoted_string(&mut self) {
+        let mut value = String::new();
 
-        // Trim the surrounding quotes.
-        if let Some(value) = source.get(start..end) {
-            self.add_token_complete(TokenType::STRING, Literal::String(value.to_string()));
+        while !self.is_at_end() {
+            if self.peek() == '"' && self.peek_next() == '"' && self.peek_after_next() == '"' {
+                break;
+            }
+
+            let c = self.advance();
+            if c == '\n' {
+                self.newline();
+            }
+            value.push(c);
         }
-    }
 
-    // Only consume the current character if it's the one we're expecting.
-    fn match_token(&mut self, expected: char) -> bool {
         if self.is_at_end() {
-            return false;
+            self.error(String::from("Unterminated triple-quoted string."));
+            return;
         }
 
-        if let Some(c) = self.source.chars().nth(self.current) {
-            if c != expected {
-                return false;
+        // The closing """.
+        self.advance();
+        self.advance();
+        self.advance();
+
+        self.add_token_complete(TokenType::STRING, Literal::String(value));
+    }
+
+    // Consume a raw string literal, where every character up to the closing " is kept as is.
+    fn raw_string(&mut self) {
+        let mut value = String::new();
+
+        while self.peek() != '"' && !self.is_at_end() {
+            let c = self.advance();
+            if c == '\n' {
+                self.newline();
             }
+            value.push(c);
+        }
+
+        if self.is_at_end() {
+            self.error(String::from("Unterminated raw string."));
+            return;
+        }
+
+        // The closing ".
+        self.advance();
+
+        self.add_token_complete(TokenType::STRING, Literal::String(value));
+    }
+
+    /* Move on to the next line, once its newline has been consumed. A line directive
+    can put us at the last line there is, which is where we then stay. */
+    fn newline(&mut self) {
+        self.line = self.line.saturating_add(1);
+        self.line_start = self.current;
+    }
+
+    // Record an error at the character that was just consumed.
+    fn error(&mut self, message: String) {
+        let column = (self.current - self.line_start).max(1) as u32;
+        self.scanned
+            .push_back(Err(ScanError::new(self.line, column, message)));
+    }
+
+    // Only consume the current character if it's the one we're expecting.
+    fn match_token(&mut self, expected: char) -> bool {
+        if self.is_at_end() || self.source[self.current] != expected {
+            return false;
         }
 
         self.current += 1;
 
-        return true;
+        true
     }
 
     // Look at the current character and return it.
     // This does not consume the character.
     fn peek(&mut self) -> char {
-        if self.is_at_end() {
-            return '\0';
+        match self.source.get(self.current) {
+            Some(c) => *c,
+            None => '\0',
         }
-
-        if let Some(c) = self.source.chars().nth(self.current) {
-            return c;
-        }
-
-        panic!("")
     }
 
     // Look ahead at the next character and return it.
     // This does not consume the character.
     fn peek_next(&mut self) -> char {
-        if (self.current + 1) >= self.source.len() {
-            return '\0';
+        match self.source.get(self.current + 1) {
+            Some(c) => *c,
+            None => '\0',
         }
+    }
 
-        if let Some(c) = self.source.chars().nth(self.current + 1) {
-            return c;
+    // Look ahead at the character after the next one and return it.
+    // This does not consume the character.
+    fn peek_after_next(&mut self) -> char {
+        match self.source.get(self.current + 2) {
+            Some(c) => *c,
+            None => '\0',
         }
-
-        panic!("Failed to peek next char in source!");
     }
 
-    // Check if the character is an alpha including an underscore.
+    // Check if the character is an alpha including an underscore or a dollar sign.
     fn is_alpha(&self, c: char) -> bool {
-        (c >= 'a' && c <= 'z') || (c >= 'A' && c <= 'Z') || c == '_'
+        c.is_ascii_lowercase() || c.is_ascii_uppercase() || c == '_' || c == '$'
     }
 
-    // Check if the character is an alpha numeric including an underscore.
+    // Check if the character is an alpha numeric including an underscore or a dollar sign.
     fn is_alpha_numeric(&self, c: char) -> bool {
         self.is_alpha(c) || self.is_digit(c)
     }
 
     // Check if the character is between the digits 0 and 9.
     fn is_digit(&self, c: char) -> bool {
-        c >= '0' && c <= '9'
+        c.is_ascii_digit()
     }
 
     // Check to see if we consumed all of the characters.
     fn is_at_end(&mut self) -> bool {
-        self.current >= self.source.len() - 1
+        self.current >= self.source.len()
     }
 
-    // Consume the next character and return it.
+    // Consume the next character and return it, or '\0' if there's nothing left.
     fn advance(&mut self) -> char {
-        if let Some(c) = self.source.chars().nth(self.current) {
-            self.current += 1;
-            return c;
+        match self.source.get(self.current) {
+            Some(c) => {
+                self.current += 1;
+                *c
+            }
+            None => '\0',
         }
+    }
 
-        panic!("Failed to advance!")
+    // The text of the lexeme being scanned.
+    fn lexeme(&self) -> String {
+        self.source[self.start..self.current].iter().collect()
     }
 
     // Take the lexeme literal to create a new token from it and
@@ -339,9 +725,461 @@ impl Scanner {
     // Take the lexeme literal to create a new token from it and
     // add it to tokens.
     fn add_token_complete(&mut self, token_type: TokenType, literal: Literal) {
-        if let Some(text) = self.source.get(self.start..self.current) {
-            self.tokens
-                .push(Token::new(token_type, text.to_string(), literal, self.line))
+        let text = self.lexeme();
+        self.scanned.push_back(Ok(Token::new(
+            token_type,
+            text,
+            literal,
+            self.line,
+            self.column,
+        )))
+    }
+}
+
+/* Hands out tokens as they're scanned, so a large source doesn't need all of its
+tokens in memory at once. Errors come out in place among the tokens, and the last
+item is always the EOF token. */
+impl Iterator for Scanner {
+    type Item = Result<Token, ScanError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        // Some lexemes, such as comments, don't produce anything.
+        while self.scanned.is_empty() && !self.finished {
+            self.scan_next();
+        }
+        self.scanned.pop_front()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Literal, Scanner, TokenType};
+    use crate::diagnostics::Diagnostics;
+    use crate::testing::scan;
+
+    // The lexemes scanned from the source with the given flags defined, and any error messages.
+    fn scan_with(source: &str, defines: &[&str]) -> (Vec<String>, Vec<String>) {
+        let mut scanner = Scanner::new(String::from(source));
+        for name in defines {
+            scanner.define(String::from(*name));
+        }
+        let mut diagnostics = Diagnostics::new(String::from("<test>"), source);
+        let tokens = scanner.scan_tokens(&mut diagnostics);
+        let lexemes = tokens.into_iter().map(|token| token.lexeme).collect();
+        let messages = diagnostics
+            .sorted()
+            .iter()
+            .map(|diagnostic| diagnostic.message.clone())
+            .collect();
+        (lexemes, messages)
+    }
+
+    #[test]
+    fn if_directive_includes_defined_branch() {
+        let source = "#if DEBUG\nyes\n#else\nno\n#endif\n";
+        let (lexemes, messages) = scan_with(source, &["DEBUG"]);
+        assert_eq!(lexemes, ["yes", ""]);
+        assert!(messages.is_empty());
+    }
+
+    #[test]
+    fn if_directive_includes_else_branch_when_undefined() {
+        let source = "#if DEBUG\nyes\n#else\nno\n#endif\n";
+        let (lexemes, messages) = scan_with(source, &[]);
+        assert_eq!(lexemes, ["no", ""]);
+        assert!(messages.is_empty());
+    }
+
+    #[test]
+    fn unbalanced_directives_are_errors() {
+        let (_, messages) = scan_with("#if DEBUG\nyes\n", &["DEBUG"]);
+        assert_eq!(messages, ["Unterminated '#if' directive."]);
+        let (_, messages) = scan_with("#endif\n", &[]);
+        assert_eq!(messages, ["'#endif' without matching '#if'."]);
+    }
+
+    // The types of the tokens scanned from the source, keeping whitespace if asked to.
+    fn token_types(source: &str, whitespace: bool) -> Vec<TokenType> {
+        let mut scanner = Scanner::new(String::from(source));
+        if whitespace {
+            scanner.keep_whitespace();
+        }
+        let mut diagnostics = Diagnostics::new(String::from("<test>"), source);
+        let tokens = scanner.scan_tokens(&mut diagnostics);
+        tokens.into_iter().map(|token| token.token_type).collect()
+    }
+
+    #[test]
+    fn whitespace_mode_keeps_newlines() {
+        use TokenType::*;
+        assert_eq!(
+            token_types("a;\n\nb;", true),
+            [IDENTIFIER, SEMICOLON, NEWLINE, NEWLINE, IDENTIFIER, SEMICOLON, EOF]
+        );
+        assert_eq!(
+            token_types("a; b;", true),
+            [IDENTIFIER, SEMICOLON, WHITESPACE, IDENTIFIER, SEMICOLON, EOF]
+        );
+    }
+
+    #[test]
+    fn whitespace_is_skipped_by_default() {
+        use TokenType::*;
+        assert_eq!(
+            token_types("a;\n\nb;", false),
+            [IDENTIFIER, SEMICOLON, IDENTIFIER, SEMICOLON, EOF]
+        );
+    }
+
+    #[test]
+    fn backslash_newline_continues_string() {
+        let (tokens, _) = scan("\"one \\\ntwo\" x");
+        assert_eq!(tokens[0].literal, Literal::String(String::from("one two")));
+        assert_eq!(tokens[1].line, 2);
+    }
+
+    #[test]
+    fn backslash_at_end_of_input() {
+        assert_eq!(
+            scan_with("\"one \\", &[]).1,
+            ["Unterminated line continuation."]
+        );
+    }
+
+    #[test]
+    fn random_input_never_panics() {
+        // Mostly characters the scanner treats specially, so the input reaches more of it.
+        let characters: Vec<char> = "\"/*#.09a_=!<\n é😀".chars().collect();
+        const DIRECTIVES: &[&str] = &[
+            "#if A\n",
+            "#else\n",
+            "#endif\n",
+            "//#line 9\n",
+            "//#line 4294967295\n",
+        ];
+        let mut seed: u64 = 0x2545_f491_4f6c_dd1d;
+        let mut next = move || {
+            seed = seed
+                .wrapping_mul(6_364_136_223_846_793_005)
+                .wrapping_add(1_442_695_040_888_963_407);
+            (seed >> 33) as usize
+        };
+
+        for _ in 0..2000 {
+            let mut bytes = Vec::new();
+            for _ in 0..next() % 64 {
+                match next() % 4 {
+                    0 => bytes.push(next() as u8),
+                    1 => bytes.extend_from_slice(DIRECTIVES[next() % DIRECTIVES.len()].as_bytes()),
+                    _ => {
+                        let character = characters[next() % characters.len()];
+                        bytes.extend_from_slice(character.to_string().as_bytes());
+                    }
+                }
+            }
+            let source = String::from_utf8_lossy(&bytes).into_owned();
+            scan(&source);
+            let mut diagnostics = Diagnostics::new(String::from("<test>"), &source);
+            let mut scanner = Scanner::new(source.clone());
+            scanner.keep_whitespace();
+            scanner.scan_tokens(&mut diagnostics);
+        }
+    }
+
+    #[test]
+    fn multibyte_comment_before_identifier_and_number() {
+        let (tokens, diagnostics) = scan("// 😀 héllo\nanswer 42.5");
+        assert!(!diagnostics.has_errors());
+        assert_eq!(tokens[0].lexeme, "answer");
+        assert_eq!(tokens[1].lexeme, "42.5");
+        assert_eq!(tokens[1].literal, Literal::Number(42.5));
+        assert_eq!(tokens[1].line, 2);
+    }
+
+    #[test]
+    fn trailing_dot_is_an_error_but_still_a_number() {
+        let (tokens, diagnostics) = scan("123.");
+        assert!(diagnostics.has_errors());
+        assert_eq!(tokens[0].token_type, TokenType::NUMBER);
+        assert_eq!(tokens[0].literal, Literal::Number(123.0));
+    }
+
+    #[test]
+    fn dot_before_name_is_method_access() {
+        use TokenType::*;
+        let (_, diagnostics) = scan("123.foo");
+        assert!(!diagnostics.has_errors());
+        assert_eq!(
+            token_types("123.foo", false),
+            [NUMBER, DOT, IDENTIFIER, EOF]
+        );
+    }
+
+    #[test]
+    fn dollar_and_underscore_identifiers() {
+        use TokenType::*;
+        for source in &["$x", "_1", "a$b", "$"] {
+            let (tokens, _) = scan(source);
+            assert_eq!(tokens[0].lexeme, *source);
+            assert_eq!(token_types(source, false), [IDENTIFIER, EOF]);
+        }
+        assert_eq!(token_types("1$", false), [NUMBER, IDENTIFIER, EOF]);
+        assert_eq!(token_types("$var var", false), [IDENTIFIER, VAR, EOF]);
+    }
+
+    #[test]
+    fn line_directive_sets_the_next_line() {
+        let (tokens, diagnostics) = scan("a\n//#line 100\n@ b");
+        assert_eq!(diagnostics.sorted()[0].line, 100);
+        assert_eq!(tokens[1].line, 100);
+    }
+
+    #[test]
+    fn malformed_line_directives_are_comments() {
+        for source in &[
+            "//#line\na",
+            "//#line x\na",
+            "//#line 0\na",
+            "//#line 4294967296\na",
+        ] {
+            let (tokens, diagnostics) = scan(source);
+            assert!(!diagnostics.has_errors());
+            assert_eq!(tokens[0].line, 2);
+        }
+    }
+
+    #[test]
+    fn line_directive_at_last_line_stays_there() {
+        let (tokens, diagnostics) = scan("//#line 4294967295\n\n\na");
+        assert!(!diagnostics.has_errors());
+        assert_eq!(tokens[0].line, u32::MAX);
+    }
+
+    #[test]
+    fn crlf_counts_as_one_line() {
+        let lines = |source: &str| -> Vec<u32> {
+            let (tokens, diagnostics) = scan(source);
+            assert!(!diagnostics.has_errors());
+            tokens.iter().map(|token| token.line).collect()
+        };
+        let source = "a\n\"b\nc\"\n/* d\n */ e\n";
+        assert_eq!(lines(&source.replace('\n', "\r\n")), lines(source));
+        assert_eq!(lines(source), [1, 3, 5, 6]);
+    }
+
+    #[test]
+    fn crlf_file_of_three_lines() {
+        let (tokens, _) = scan("var a = 1;\r\nvar b = 2;\r\nprint a + b;");
+        assert_eq!(tokens.last().map(|token| token.line), Some(3));
+    }
+
+    #[test]
+    fn scans_thousands_of_identifiers_and_keywords() {
+        let words = ["alpha", "var", "beta_2", "while", "fun", "gamma"];
+        let source: Vec<&str> = words.iter().cycle().take(6000).cloned().collect();
+        let (tokens, diagnostics) = scan(&source.join(" "));
+        assert!(!diagnostics.has_errors());
+        assert_eq!(tokens.len(), 6001);
+        for (token, word) in tokens[..6000].iter().zip(words.iter().cycle()) {
+            assert_eq!(token.lexeme, *word);
         }
+        assert_eq!(tokens[1].token_type, TokenType::VAR);
+        assert_eq!(tokens[5999].token_type, TokenType::IDENTIFIER);
+    }
+
+    #[test]
+    fn scans_a_large_file() {
+        let line = "var name = \"text é\" + 12.5; // comment 😀\n";
+        let (tokens, diagnostics) = scan(&line.repeat(2000));
+        assert!(!diagnostics.has_errors());
+        assert_eq!(tokens.len(), 2000 * 7 + 1);
+        let last = &tokens[2000 * 7 - 1];
+        assert_eq!(last.token_type, TokenType::SEMICOLON);
+        assert_eq!(last.line, 2000);
+        assert_eq!(
+            tokens[2000 * 7 - 4].literal,
+            Literal::String(String::from("text é"))
+        );
+        assert_eq!(tokens[2000 * 7 - 2].literal, Literal::Number(12.5));
+    }
+
+    #[test]
+    fn iterating_matches_scan_tokens() {
+        let source = "var a = \"x\";\n@ print a + 1.5; // done\n";
+        let (expected, _) = scan(source);
+
+        let items: Vec<_> = Scanner::new(String::from(source)).collect();
+        assert_eq!(items.iter().filter(|item| item.is_err()).count(), 1);
+        let tokens: Vec<_> = items.into_iter().filter_map(Result::ok).collect();
+        assert_eq!(format!("{:?}", tokens), format!("{:?}", expected));
+
+        let eofs = tokens
+            .iter()
+            .filter(|token| token.token_type == TokenType::EOF);
+        assert_eq!(eofs.count(), 1);
+        assert_eq!(tokens[tokens.len() - 1].token_type, TokenType::EOF);
+    }
+
+    #[test]
+    fn iterator_ends_after_eof() {
+        let mut scanner = Scanner::new(String::from("a"));
+        assert!(scanner.next().is_some());
+        assert!(scanner.next().is_some());
+        assert!(scanner.next().is_none());
+        assert!(scanner.next().is_none());
+    }
+
+    #[test]
+    fn reset_scans_like_a_fresh_scanner() {
+        let first = "var a = 1;\n#if A\nb\n#endif\n";
+        let second = "print \"two\nlines\" c;";
+        let mut scanner = Scanner::new(String::from(first));
+        scanner.keep_whitespace();
+        let mut diagnostics = Diagnostics::new(String::from("<test>"), first);
+        let reused_first = scanner.scan_tokens(&mut diagnostics);
+        scanner.reset(String::from(second));
+        let mut diagnostics = Diagnostics::new(String::from("<test>"), second);
+        let reused_second = scanner.scan_tokens(&mut diagnostics);
+
+        let fresh = |source: &str| {
+            let mut scanner = Scanner::new(String::from(source));
+            scanner.keep_whitespace();
+            let mut diagnostics = Diagnostics::new(String::from("<test>"), source);
+            scanner.scan_tokens(&mut diagnostics)
+        };
+        assert_eq!(format!("{:?}", reused_first), format!("{:?}", fresh(first)));
+        assert_eq!(
+            format!("{:?}", reused_second),
+            format!("{:?}", fresh(second))
+        );
+    }
+
+    #[test]
+    fn reset_keeps_defined_flags() {
+        let mut scanner = Scanner::new(String::new());
+        scanner.define(String::from("A"));
+        scanner.reset(String::from("#if A\nyes\n#endif\n"));
+        let mut diagnostics = Diagnostics::new(String::from("<test>"), "");
+        let tokens = scanner.scan_tokens(&mut diagnostics);
+        assert_eq!(tokens[0].lexeme, "yes");
+    }
+
+    #[test]
+    fn raw_strings_keep_backslashes() {
+        let (tokens, diagnostics) = scan(r#"r"a\nb" "a\nb" r"C:\path\""#);
+        assert!(!diagnostics.has_errors());
+        assert_eq!(tokens[0].literal, Literal::String(String::from(r"a\nb")));
+        assert_eq!(tokens[1].literal, Literal::String(String::from(r"a\nb")));
+        assert_eq!(
+            tokens[2].literal,
+            Literal::String(String::from(r"C:\path\"))
+        );
+    }
+
+    #[test]
+    fn raw_strings_span_lines() {
+        let (tokens, _) = scan("r\"a\nb\" c");
+        assert_eq!(tokens[0].literal, Literal::String(String::from("a\nb")));
+        assert_eq!(tokens[1].line, 2);
+        let (_, diagnostics) = scan("r\"open");
+        assert!(diagnostics.has_errors());
+    }
+
+    #[test]
+    fn r_alone_is_still_an_identifier() {
+        use TokenType::*;
+        assert_eq!(
+            token_types("r rx r \"s\"", false),
+            [IDENTIFIER, IDENTIFIER, IDENTIFIER, STRING, EOF]
+        );
+    }
+
+    #[test]
+    fn triple_quoted_string_keeps_newlines() {
+        let (tokens, diagnostics) = scan("\"\"\"one\n  \"two\"\nthree\"\"\" after");
+        assert!(!diagnostics.has_errors());
+        let expected = String::from("one\n  \"two\"\nthree");
+        assert_eq!(tokens[0].literal, Literal::String(expected));
+        assert_eq!(tokens[0].line, 3);
+        assert_eq!(tokens[1].lexeme, "after");
+        assert_eq!(tokens[1].line, 3);
+    }
+
+    #[test]
+    fn unterminated_triple_quoted_string() {
+        let (_, diagnostics) = scan("\"\"\"open\n\"\"");
+        let messages: Vec<&str> = diagnostics
+            .sorted()
+            .iter()
+            .map(|diagnostic| diagnostic.message.as_str())
+            .collect();
+        assert_eq!(messages, ["Unterminated triple-quoted string."]);
+    }
+
+    #[test]
+    fn empty_string_is_not_triple_quoted() {
+        let (tokens, _) = scan("\"\" x");
+        assert_eq!(tokens[0].literal, Literal::String(String::new()));
+        assert_eq!(tokens[1].lexeme, "x");
+    }
+
+    #[test]
+    fn keyword_in_other_case_is_a_warning() {
+        let warnings = |source: &str| {
+            let mut scanner = Scanner::new(String::from(source));
+            let mut diagnostics = Diagnostics::new(String::from("<test>"), source);
+            scanner.scan_tokens(&mut diagnostics);
+            assert!(!diagnostics.has_errors());
+            let warnings: Vec<String> = scanner
+                .take_warnings()
+                .iter()
+                .map(|warning| warning.to_string())
+                .collect();
+            warnings
+        };
+        assert_eq!(
+            warnings("If x\nCLASS"),
+            [
+                "1:1: 'If' is an identifier, not the keyword 'if'.",
+                "2:1: 'CLASS' is an identifier, not the keyword 'class'."
+            ]
+        );
+        assert!(warnings("iffy if classy").is_empty());
+    }
+
+    // The error messages from scanning with the given limits on identifiers and numbers.
+    fn length_errors(source: &str, max_identifier: usize, max_number: usize) -> Vec<String> {
+        let mut scanner =
+            Scanner::with_max_lengths(String::from(source), max_identifier, max_number);
+        let mut diagnostics = Diagnostics::new(String::from("<test>"), source);
+        scanner.scan_tokens(&mut diagnostics);
+        diagnostics
+            .sorted()
+            .iter()
+            .map(|diagnostic| diagnostic.message.clone())
+            .collect()
+    }
+
+    #[test]
+    fn identifiers_up_to_the_limit_scan() {
+        assert!(length_errors("abcd", 4, 4).is_empty());
+        assert_eq!(length_errors("abcde", 4, 4), ["Identifier is too long."]);
+    }
+
+    #[test]
+    fn numbers_up_to_the_limit_scan() {
+        assert!(length_errors("12.5", 4, 4).is_empty());
+        assert_eq!(
+            length_errors("12.55", 4, 4),
+            ["Number literal is too long."]
+        );
+    }
+
+    #[test]
+    fn default_limits_are_generous() {
+        let (_, diagnostics) = scan(&"a".repeat(1024));
+        assert!(!diagnostics.has_errors());
+        let (_, diagnostics) = scan(&"a".repeat(1025));
+        assert!(diagnostics.has_errors());
     }
 }
@@ -0,0 +1,53 @@
+use std::error::Error;
+use std::fmt;
+
+// A problem with the source found while scanning, such as an unexpected character.
+#[derive(Debug)]
+pub struct ScanError {
+    pub line: u32,
+    pub column: u32,
+    pub message: String,
+}
+
+impl ScanError {
+    pub fn new(line: u32, column: u32, message: String) -> Self {
+        ScanError {
+            line,
+            column,
+            message,
+        }
+    }
+}
+
+// Where the problem is followed by what it is, such as "3:7: Unterminated string.".
+impl fmt::Display for ScanError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}: {}", self.line, self.column, self.message)
+    }
+}
+
+impl Error for ScanError {}
+
+#[cfg(test)]
+mod tests {
+    use std::error::Error;
+
+    use super::ScanError;
+
+    #[test]
+    fn displays_position_and_message() {
+        let error = ScanError::new(3, 7, String::from("Unterminated string."));
+        assert_eq!(error.to_string(), "3:7: Unterminated string.");
+    }
+
+    #[test]
+    fn can_be_boxed_as_an_error() {
+        fn scan() -> Result<(), Box<dyn Error>> {
+            Err(ScanError::new(1, 2, String::from("Unexpected character.")))?;
+            Ok(())
+        }
+        let error = scan().unwrap_err();
+        assert_eq!(error.to_string(), "1:2: Unexpected character.");
+        assert!(error.source().is_none());
+    }
+}
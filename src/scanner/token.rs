@@ -1,45 +1,106 @@
-mod token_type;
 mod literal;
+mod token_type;
 
+pub use literal::{Literal, LiteralKey, NilStyle};
 pub use token_type::TokenType;
-pub use literal::Literal;
 
 #[derive(Debug, Clone)]
 pub struct Token {
-    token_type: TokenType,
-    lexeme: String,
-    literal: Literal,
-    line: u32,
+    pub token_type: TokenType,
+    pub lexeme: String,
+    pub literal: Literal,
+    pub line: u32,
+    pub column: u32, // Of the first character, counting from 1.
 }
 
 impl Token {
-    pub fn new(token_type: TokenType, lexeme: String, literal: Literal, line: u32) -> Self {
+    pub fn new(
+        token_type: TokenType,
+        lexeme: String,
+        literal: Literal,
+        line: u32,
+        column: u32,
+    ) -> Self {
         Token {
             token_type,
             lexeme,
             literal,
             line,
+            column,
         }
     }
 
-    // Used to show where a particular warning or error is.
-    pub fn to_string(&mut self) -> String {
-        // Since Crystal handles nil differently to Java's null, in order to pass the provided tests, 
-        // we need to modify the output so it behaves like the Java implementation.
-        let literal: String = match self.literal.clone() {
-            Literal::Bool(value) => format!("{}", value),
-            Literal::Number(value) => {
-                let mut x = value.to_string();
-
-                if value.fract() == 0.0 {
-                    x = format!("{}.0", value);
-                }
-                x
-            }
-            Literal::String(value) => value,
+    // The token's type, lexeme and value, with nil spelled the given way.
+    pub fn describe(&self, nil_style: NilStyle) -> String {
+        format!(
+            "{:?} {} {}",
+            self.token_type,
+            self.lexeme,
+            self.literal.display(nil_style)
+        )
+    }
+
+    // The token as a JSON object, for tools that want to read the token stream.
+    pub fn to_json(&self) -> String {
+        let literal = match &self.literal {
+            Literal::Bool(value) => value.to_string(),
+            Literal::Number(value) => value.to_string(),
+            Literal::String(value) => json_string(value),
             Literal::Nil => String::from("null"),
+            _ => unreachable!("Only scanned values appear in tokens."),
         };
+        format!(
+            "{{\"type\": {}, \"lexeme\": {}, \"literal\": {}, \"line\": {}}}",
+            json_string(&format!("{:?}", self.token_type)),
+            json_string(&self.lexeme),
+            literal,
+            self.line
+        )
+    }
+}
+
+// Quote the text as a JSON string, escaping anything JSON doesn't allow as it is.
+fn json_string(text: &str) -> String {
+    let mut json = String::from("\"");
+    for c in text.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            '\r' => json.push_str("\\r"),
+            '\t' => json.push_str("\\t"),
+            c if (c as u32) < 0x20 => json.push_str(&format!("\\u{:04x}", c as u32)),
+            c => json.push(c),
+        }
+    }
+    json.push('"');
+    json
+}
+
+#[cfg(test)]
+mod tests {
+    use super::NilStyle;
+    use crate::testing::scan;
+
+    #[test]
+    fn tokens_as_json() {
+        let (tokens, _) = scan("1 \"a\\b\nc\" nil");
+        let json: Vec<String> = tokens.iter().map(|token| token.to_json()).collect();
+        assert_eq!(
+            json,
+            [
+                r#"{"type": "NUMBER", "lexeme": "1", "literal": 1, "line": 1}"#,
+                r#"{"type": "STRING", "lexeme": "\"a\\b\nc\"", "literal": "a\\b\nc", "line": 2}"#,
+                r#"{"type": "NIL", "lexeme": "nil", "literal": null, "line": 2}"#,
+                r#"{"type": "EOF", "lexeme": "", "literal": null, "line": 2}"#,
+            ]
+        );
+    }
 
-        format!("{:?} {} {}", self.token_type, self.lexeme, literal,)
+    #[test]
+    fn describe_spells_nil_either_way() {
+        let (tokens, _) = scan("x");
+        assert_eq!(tokens[0].describe(NilStyle::Nil), "IDENTIFIER x nil");
+        assert_eq!(tokens[0].describe(NilStyle::Null), "IDENTIFIER x null");
     }
 }
@@ -1,7 +1,361 @@
+use std::cell::RefCell;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::rc::Rc;
+
+use crate::interpreter::{LoxClass, LoxFunction, LoxInstance, LoxMap, NativeFunction};
+
 #[derive(Debug, Clone)]
 pub enum Literal {
     Bool(bool),
     Number(f64),
     String(String),
+    Function(Rc<LoxFunction>),
+    Native(Rc<NativeFunction>),
+    Class(Rc<LoxClass>),
+    Instance(Rc<RefCell<LoxInstance>>), // Shared so that field changes are seen everywhere.
+    List(Rc<RefCell<Vec<Literal>>>),    // Shared so that element changes are seen everywhere.
+    Map(Rc<RefCell<LoxMap>>),           // Shared so that entry changes are seen everywhere.
     Nil,
 }
+
+// How nil is spelled when shown: 'nil' as in the book, or 'null' as in the Java implementation.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum NilStyle {
+    Nil,
+    Null,
+}
+
+impl Literal {
+    // Show the value with nil spelled the given way, including inside lists and records.
+    pub fn display(&self, nil_style: NilStyle) -> LiteralDisplay<'_> {
+        LiteralDisplay {
+            literal: self,
+            nil_style,
+        }
+    }
+}
+
+// A value along with how to spell nil, ready to be formatted.
+pub struct LiteralDisplay<'a> {
+    literal: &'a Literal,
+    nil_style: NilStyle,
+}
+
+// Values shown on their own spell nil the way the book does.
+impl fmt::Display for Literal {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.display(NilStyle::Nil))
+    }
+}
+
+/* The text shown to the user, as print shows it. Whole numbers keep a ".0" so they
+still look like the floats they are, and strings are shown without quotes. */
+impl fmt::Display for LiteralDisplay<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.write(f, self.literal, &mut Vec::new())
+    }
+}
+
+impl LiteralDisplay<'_> {
+    /* Write the value, given the lists, maps and records it's inside of. One that
+    contains itself is shown as [...], {...} or Name(...) the second time round
+    instead of being written forever. */
+    fn write(
+        &self,
+        f: &mut fmt::Formatter,
+        literal: &Literal,
+        printing: &mut Vec<*const ()>,
+    ) -> fmt::Result {
+        match literal {
+            Literal::Bool(value) => write!(f, "{}", value),
+            Literal::Number(value) if value.fract() == 0.0 => write!(f, "{}.0", value),
+            Literal::Number(value) => write!(f, "{}", value),
+            Literal::String(value) => write!(f, "{}", value),
+            Literal::Function(function) => write!(f, "{}", function),
+            Literal::Native(native) => write!(f, "{}", native),
+            Literal::Class(class) => write!(f, "{}", class),
+            // Records show their fields, such as Point(1.0, 2.0).
+            Literal::Instance(instance) => {
+                let pointer = Rc::as_ptr(instance) as *const ();
+                let instance = instance.borrow();
+                let values = match instance.record_values() {
+                    Some(values) => values,
+                    None => return write!(f, "{}", instance),
+                };
+                write!(f, "{}(", instance.class().name())?;
+                if printing.contains(&pointer) {
+                    write!(f, "...")?;
+                } else {
+                    printing.push(pointer);
+                    self.write_all(f, values.iter(), printing)?;
+                    printing.pop();
+                }
+                write!(f, ")")
+            }
+            Literal::List(list) => {
+                let pointer = Rc::as_ptr(list) as *const ();
+                write!(f, "[")?;
+                if printing.contains(&pointer) {
+                    write!(f, "...")?;
+                } else {
+                    printing.push(pointer);
+                    self.write_all(f, list.borrow().iter(), printing)?;
+                    printing.pop();
+                }
+                write!(f, "]")
+            }
+            Literal::Map(map) => {
+                let pointer = Rc::as_ptr(map) as *const ();
+                write!(f, "{{")?;
+                if printing.contains(&pointer) {
+                    write!(f, "...")?;
+                } else {
+                    printing.push(pointer);
+                    for (index, (key, value)) in map.borrow().entries().iter().enumerate() {
+                        if index > 0 {
+                            write!(f, ", ")?;
+                        }
+                        self.write(f, key.literal(), printing)?;
+                        write!(f, ": ")?;
+                        self.write(f, value, printing)?;
+                    }
+                    printing.pop();
+                }
+                write!(f, "}}")
+            }
+            Literal::Nil => match self.nil_style {
+                NilStyle::Nil => write!(f, "nil"),
+                NilStyle::Null => write!(f, "null"),
+            },
+        }
+    }
+
+    // Write the values separated by commas.
+    fn write_all<'b>(
+        &self,
+        f: &mut fmt::Formatter,
+        values: impl Iterator<Item = &'b Literal>,
+        printing: &mut Vec<*const ()>,
+    ) -> fmt::Result {
+        for (index, value) in values.enumerate() {
+            if index > 0 {
+                write!(f, ", ")?;
+            }
+            self.write(f, value, printing)?;
+        }
+        Ok(())
+    }
+}
+
+/* Equality for Rust code, which unlike Lox's == never compares records by their
+fields. Numbers follow IEEE, so NaN isn't equal to itself, and everything else that
+isn't a plain value is shared, so it's only equal to itself. */
+impl PartialEq for Literal {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Literal::Bool(left), Literal::Bool(right)) => left == right,
+            (Literal::Number(left), Literal::Number(right)) => left == right,
+            (Literal::String(left), Literal::String(right)) => left == right,
+            (Literal::Function(left), Literal::Function(right)) => Rc::ptr_eq(left, right),
+            (Literal::Native(left), Literal::Native(right)) => Rc::ptr_eq(left, right),
+            (Literal::Class(left), Literal::Class(right)) => Rc::ptr_eq(left, right),
+            (Literal::Instance(left), Literal::Instance(right)) => Rc::ptr_eq(left, right),
+            (Literal::List(left), Literal::List(right)) => Rc::ptr_eq(left, right),
+            (Literal::Map(left), Literal::Map(right)) => Rc::ptr_eq(left, right),
+            (Literal::Nil, Literal::Nil) => true,
+            _ => false,
+        }
+    }
+}
+
+/* A value that can be used as the key of a hash map. Only plain values can be keys,
+since the others can change or are only equal to themselves. Numbers are compared
+by their bits after making -0 the same as 0 and every NaN the same NaN, so unlike
+Literal a NaN key can be found again. */
+#[derive(Debug, Clone)]
+pub struct LiteralKey(Literal);
+
+impl LiteralKey {
+    pub fn new(literal: Literal) -> Result<Self, String> {
+        match literal {
+            Literal::Bool(_) | Literal::Number(_) | Literal::String(_) | Literal::Nil => {
+                Ok(LiteralKey(literal))
+            }
+            _ => Err(String::from(
+                "Only numbers, strings, booleans and nil can be used as keys.",
+            )),
+        }
+    }
+
+    pub fn literal(&self) -> &Literal {
+        &self.0
+    }
+}
+
+fn number_bits(value: f64) -> u64 {
+    if value.is_nan() {
+        f64::NAN.to_bits()
+    } else if value == 0.0 {
+        0
+    } else {
+        value.to_bits()
+    }
+}
+
+impl PartialEq for LiteralKey {
+    fn eq(&self, other: &Self) -> bool {
+        match (&self.0, &other.0) {
+            (Literal::Number(left), Literal::Number(right)) => {
+                number_bits(*left) == number_bits(*right)
+            }
+            (left, right) => left == right,
+        }
+    }
+}
+
+impl Eq for LiteralKey {}
+
+impl Hash for LiteralKey {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        match &self.0 {
+            Literal::Bool(value) => (0, value).hash(state),
+            Literal::Number(value) => (1, number_bits(*value)).hash(state),
+            Literal::String(value) => (2, value).hash(state),
+            _ => 3.hash(state),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::collections::hash_map::DefaultHasher;
+    use std::collections::HashMap;
+    use std::hash::{Hash, Hasher};
+    use std::rc::Rc;
+
+    use super::{Literal, LiteralKey, NilStyle};
+    use crate::interpreter::{LoxMap, NativeFunction};
+
+    #[test]
+    fn plain_values_compare_by_value() {
+        assert_eq!(Literal::Number(1.5), Literal::Number(1.5));
+        assert_ne!(Literal::Number(1.5), Literal::Number(2.0));
+        assert_eq!(Literal::Number(0.0), Literal::Number(-0.0));
+        assert_ne!(Literal::Number(f64::NAN), Literal::Number(f64::NAN));
+        assert_eq!(
+            Literal::String(String::from("a")),
+            Literal::String(String::from("a"))
+        );
+        assert_ne!(
+            Literal::String(String::from("a")),
+            Literal::String(String::from("b"))
+        );
+        assert_eq!(Literal::Bool(true), Literal::Bool(true));
+        assert_ne!(Literal::Bool(true), Literal::Bool(false));
+        assert_eq!(Literal::Nil, Literal::Nil);
+    }
+
+    #[test]
+    fn different_types_are_not_equal() {
+        assert_ne!(Literal::Number(0.0), Literal::Nil);
+        assert_ne!(Literal::Bool(false), Literal::Nil);
+        assert_ne!(Literal::String(String::from("1")), Literal::Number(1.0));
+    }
+
+    #[test]
+    fn shared_values_are_only_equal_to_themselves() {
+        let list = Literal::List(Rc::new(RefCell::new(vec![Literal::Nil])));
+        assert_eq!(list, list.clone());
+        assert_ne!(
+            list,
+            Literal::List(Rc::new(RefCell::new(vec![Literal::Nil])))
+        );
+    }
+
+    fn hash_of(key: &LiteralKey) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        key.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    fn key(literal: Literal) -> LiteralKey {
+        LiteralKey::new(literal).expect("Plain values can be keys.")
+    }
+
+    #[test]
+    fn equal_numbers_are_the_same_key() {
+        let one = key(Literal::Number(1.0));
+        let one_point_zero = key(Literal::Number("1.0".parse().unwrap()));
+        assert_eq!(one, one_point_zero);
+        assert_eq!(hash_of(&one), hash_of(&one_point_zero));
+
+        let zero = key(Literal::Number(0.0));
+        let negative_zero = key(Literal::Number(-0.0));
+        assert_eq!(zero, negative_zero);
+        assert_eq!(hash_of(&zero), hash_of(&negative_zero));
+
+        let nan = key(Literal::Number(f64::NAN));
+        let other_nan = key(Literal::Number(-f64::NAN));
+        assert_eq!(nan, other_nan);
+        assert_eq!(hash_of(&nan), hash_of(&other_nan));
+    }
+
+    // Clippy can't tell that a key only ever holds plain values, which can't change.
+    #[test]
+    #[allow(clippy::mutable_key_type)]
+    fn equal_strings_collide_in_a_hash_map() {
+        let mut map = HashMap::new();
+        map.insert(key(Literal::String(String::from("a"))), 1);
+        map.insert(key(Literal::String(String::from("a"))), 2);
+        assert_eq!(map.len(), 1);
+        assert_eq!(map[&key(Literal::String(String::from("a")))], 2);
+        assert!(!map.contains_key(&key(Literal::Number(1.0))));
+    }
+
+    #[test]
+    fn functions_cannot_be_keys() {
+        let native = NativeFunction::new(String::from("f"), 0, |_, _| Ok(Literal::Nil));
+        let error = LiteralKey::new(Literal::Native(Rc::new(native))).unwrap_err();
+        assert_eq!(
+            error,
+            "Only numbers, strings, booleans and nil can be used as keys."
+        );
+    }
+
+    #[test]
+    fn display_of_each_value() {
+        assert_eq!(Literal::Bool(true).to_string(), "true");
+        assert_eq!(Literal::Number(3.0).to_string(), "3.0");
+        assert_eq!(Literal::Number(-0.5).to_string(), "-0.5");
+        assert_eq!(Literal::String(String::from("a b")).to_string(), "a b");
+        assert_eq!(Literal::Nil.to_string(), "nil");
+        let native = NativeFunction::new(String::from("f"), 0, |_, _| Ok(Literal::Nil));
+        assert_eq!(Literal::Native(Rc::new(native)).to_string(), "<native f>");
+
+        let list = vec![Literal::Number(1.0), Literal::String(String::from("x"))];
+        let list = Literal::List(Rc::new(RefCell::new(list)));
+        assert_eq!(list.to_string(), "[1.0, x]");
+
+        let mut map = LoxMap::new();
+        map.insert(key(Literal::Number(1.0)), Literal::Bool(false));
+        map.insert(key(Literal::String(String::from("k"))), Literal::Nil);
+        assert_eq!(
+            Literal::Map(Rc::new(RefCell::new(map))).to_string(),
+            "{1.0: false, k: nil}"
+        );
+    }
+
+    #[test]
+    fn nil_style_applies_inside_lists_and_maps() {
+        let list = Literal::List(Rc::new(RefCell::new(vec![Literal::Nil])));
+        assert_eq!(list.display(NilStyle::Null).to_string(), "[null]");
+        assert_eq!(Literal::Nil.display(NilStyle::Null).to_string(), "null");
+
+        let mut map = LoxMap::new();
+        map.insert(key(Literal::Nil), Literal::Nil);
+        let map = Literal::Map(Rc::new(RefCell::new(map)));
+        assert_eq!(map.display(NilStyle::Null).to_string(), "{null: null}");
+    }
+}
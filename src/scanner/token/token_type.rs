@@ -1,13 +1,19 @@
-#[derive(Debug, Clone)]
+// Variant names follow the book's grammar notation.
+#[allow(non_camel_case_types, clippy::upper_case_acronyms)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum TokenType {
     // Single-character tokens.
     LEFT_PAREN,
     RIGHT_PAREN,
     LEFT_BRACE,
     RIGHT_BRACE,
+    LEFT_BRACKET,
+    RIGHT_BRACKET,
+    COLON,
     COMMA,
     DOT,
     MINUS,
+    PERCENT,
     PLUS,
     SEMICOLON,
     SLASH,
@@ -22,12 +28,24 @@ pub enum TokenType {
     GREATER_EQUAL,
     LESS,
     LESS_EQUAL,
+    MINUS_EQUAL,
+    MINUS_MINUS,
+    PLUS_EQUAL,
+    PLUS_PLUS,
+    SLASH_EQUAL,
+    STAR_EQUAL,
+    STAR_STAR,
 
     // Literals.
     IDENTIFIER,
     STRING,
     NUMBER,
 
+    // The pieces of a string with '${...}' in it, around the tokens of each expression.
+    STRING_INTERP_START,
+    STRING_INTERP_MID,
+    STRING_INTERP_END,
+
     // Keywords.
     AND,
     CLASS,
@@ -39,16 +57,27 @@ pub enum TokenType {
     NIL,
     OR,
     PRINT,
+    RECORD,
     RETURN,
     SUPER,
     THIS,
     TRUE,
     VAR,
+    WHEN,
     WHILE,
 
     // Statements.
     BREAK,
+    CASE,
     CONTINUE,
+    DEFAULT,
+    DO,
+    SWITCH,
+    UNLESS,
+
+    // Layout, only produced when the scanner is asked to keep whitespace.
+    NEWLINE,
+    WHITESPACE,
 
     // Misc.
     EOF,
@@ -0,0 +1,360 @@
+use std::any::Any;
+use std::rc::Rc;
+
+use crate::expression;
+use crate::interpreter::Unwind;
+use crate::scanner::Token;
+
+// See Expression for why accepting a visitor is split into its own trait.
+pub trait Accept<T> {
+    fn accept(&self, visitor: &mut dyn Visitor<T>) -> T;
+}
+
+pub trait Statement:
+    Accept<Result<(), Unwind>> + Accept<usize> + Accept<String> + Accept<()>
+{
+    // Used by the printers to check which kind of statement they have.
+    fn as_any(&self) -> &dyn Any;
+}
+
+impl<S> Statement for S
+where
+    S: Accept<Result<(), Unwind>> + Accept<usize> + Accept<String> + Accept<()> + Any,
+{
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+pub trait Visitor<T> {
+    fn visit_block_statement(&mut self, statement: &Block) -> T;
+    fn visit_break_statement(&mut self, statement: &Break) -> T;
+    fn visit_class_statement(&mut self, statement: &Class) -> T;
+    fn visit_continue_statement(&mut self, statement: &Continue) -> T;
+    fn visit_do_while_statement(&mut self, statement: &DoWhile) -> T;
+    fn visit_expression_statement(&mut self, statement: &Expression) -> T;
+    fn visit_function_statement(&mut self, statement: &Function) -> T;
+    fn visit_if_statement(&mut self, statement: &If) -> T;
+    fn visit_print_statement(&mut self, statement: &Print) -> T;
+    fn visit_record_statement(&mut self, statement: &Record) -> T;
+    fn visit_return_statement(&mut self, statement: &Return) -> T;
+    fn visit_switch_statement(&mut self, statement: &Switch) -> T;
+    fn visit_var_statement(&mut self, statement: &Var) -> T;
+    fn visit_while_statement(&mut self, statement: &While) -> T;
+}
+
+// Block.
+
+pub struct Block {
+    pub statements: Vec<Box<dyn Statement>>,
+}
+
+impl Block {
+    pub fn new(statements: Vec<Box<dyn Statement>>) -> Self {
+        Block { statements }
+    }
+}
+
+impl<T> Accept<T> for Block {
+    fn accept(&self, visitor: &mut dyn Visitor<T>) -> T {
+        visitor.visit_block_statement(self)
+    }
+}
+
+// Break.
+
+pub struct Break;
+
+impl Break {
+    pub fn new() -> Self {
+        Break
+    }
+}
+
+impl<T> Accept<T> for Break {
+    fn accept(&self, visitor: &mut dyn Visitor<T>) -> T {
+        visitor.visit_break_statement(self)
+    }
+}
+
+// Class.
+
+pub struct Class {
+    pub name: Token,
+    pub superclass: Option<expression::Variable>,
+    pub methods: Vec<Function>,
+}
+
+impl Class {
+    pub fn new(
+        name: Token,
+        superclass: Option<expression::Variable>,
+        methods: Vec<Function>,
+    ) -> Self {
+        Class {
+            name,
+            superclass,
+            methods,
+        }
+    }
+}
+
+impl<T> Accept<T> for Class {
+    fn accept(&self, visitor: &mut dyn Visitor<T>) -> T {
+        visitor.visit_class_statement(self)
+    }
+}
+
+// Continue.
+
+pub struct Continue;
+
+impl Continue {
+    pub fn new() -> Self {
+        Continue
+    }
+}
+
+impl<T> Accept<T> for Continue {
+    fn accept(&self, visitor: &mut dyn Visitor<T>) -> T {
+        visitor.visit_continue_statement(self)
+    }
+}
+
+// DoWhile.
+
+pub struct DoWhile {
+    pub body: Box<dyn Statement>,
+    pub condition: Box<dyn expression::Expression>,
+}
+
+impl DoWhile {
+    pub fn new(body: Box<dyn Statement>, condition: Box<dyn expression::Expression>) -> Self {
+        DoWhile { body, condition }
+    }
+}
+
+impl<T> Accept<T> for DoWhile {
+    fn accept(&self, visitor: &mut dyn Visitor<T>) -> T {
+        visitor.visit_do_while_statement(self)
+    }
+}
+
+// Expression.
+
+pub struct Expression {
+    pub expression: Box<dyn expression::Expression>,
+}
+
+impl Expression {
+    pub fn new(expression: Box<dyn expression::Expression>) -> Self {
+        Expression { expression }
+    }
+}
+
+impl<T> Accept<T> for Expression {
+    fn accept(&self, visitor: &mut dyn Visitor<T>) -> T {
+        visitor.visit_expression_statement(self)
+    }
+}
+
+// Function.
+
+pub struct Function {
+    pub name: Token,
+    pub params: Vec<Token>,
+    pub guard: Option<Rc<dyn expression::Expression>>, // Shared with the functions created from it.
+    pub body: Rc<Vec<Box<dyn Statement>>>,
+}
+
+impl Function {
+    pub fn new(
+        name: Token,
+        params: Vec<Token>,
+        guard: Option<Box<dyn expression::Expression>>,
+        body: Vec<Box<dyn Statement>>,
+    ) -> Self {
+        Function {
+            name,
+            params,
+            guard: guard.map(Rc::from),
+            body: Rc::new(body),
+        }
+    }
+}
+
+impl<T> Accept<T> for Function {
+    fn accept(&self, visitor: &mut dyn Visitor<T>) -> T {
+        visitor.visit_function_statement(self)
+    }
+}
+
+// If.
+
+pub struct If {
+    pub condition: Box<dyn expression::Expression>,
+    pub then_branch: Box<dyn Statement>,
+    pub else_branch: Option<Box<dyn Statement>>,
+}
+
+impl If {
+    pub fn new(
+        condition: Box<dyn expression::Expression>,
+        then_branch: Box<dyn Statement>,
+        else_branch: Option<Box<dyn Statement>>,
+    ) -> Self {
+        If {
+            condition,
+            then_branch,
+            else_branch,
+        }
+    }
+}
+
+impl<T> Accept<T> for If {
+    fn accept(&self, visitor: &mut dyn Visitor<T>) -> T {
+        visitor.visit_if_statement(self)
+    }
+}
+
+// Print.
+
+pub struct Print {
+    pub expression: Box<dyn expression::Expression>,
+}
+
+impl Print {
+    pub fn new(expression: Box<dyn expression::Expression>) -> Self {
+        Print { expression }
+    }
+}
+
+impl<T> Accept<T> for Print {
+    fn accept(&self, visitor: &mut dyn Visitor<T>) -> T {
+        visitor.visit_print_statement(self)
+    }
+}
+
+// Record.
+
+pub struct Record {
+    pub name: Token,
+    pub fields: Vec<Token>,
+}
+
+impl Record {
+    pub fn new(name: Token, fields: Vec<Token>) -> Self {
+        Record { name, fields }
+    }
+}
+
+impl<T> Accept<T> for Record {
+    fn accept(&self, visitor: &mut dyn Visitor<T>) -> T {
+        visitor.visit_record_statement(self)
+    }
+}
+
+// Return.
+
+pub struct Return {
+    pub value: Option<Box<dyn expression::Expression>>,
+}
+
+impl Return {
+    pub fn new(value: Option<Box<dyn expression::Expression>>) -> Self {
+        Return { value }
+    }
+}
+
+impl<T> Accept<T> for Return {
+    fn accept(&self, visitor: &mut dyn Visitor<T>) -> T {
+        visitor.visit_return_statement(self)
+    }
+}
+
+// Switch.
+
+pub struct Switch {
+    pub subject: Box<dyn expression::Expression>,
+    pub cases: Vec<Case>,
+    pub default: Option<Vec<Box<dyn Statement>>>,
+}
+
+impl Switch {
+    pub fn new(
+        subject: Box<dyn expression::Expression>,
+        cases: Vec<Case>,
+        default: Option<Vec<Box<dyn Statement>>>,
+    ) -> Self {
+        Switch {
+            subject,
+            cases,
+            default,
+        }
+    }
+}
+
+impl<T> Accept<T> for Switch {
+    fn accept(&self, visitor: &mut dyn Visitor<T>) -> T {
+        visitor.visit_switch_statement(self)
+    }
+}
+
+// The statements a switch runs when its subject equals the value.
+pub struct Case {
+    pub value: Box<dyn expression::Expression>,
+    pub body: Vec<Box<dyn Statement>>,
+}
+
+impl Case {
+    pub fn new(value: Box<dyn expression::Expression>, body: Vec<Box<dyn Statement>>) -> Self {
+        Case { value, body }
+    }
+}
+
+// Var.
+
+pub struct Var {
+    pub name: Token,
+    pub initializer: Option<Box<dyn expression::Expression>>,
+}
+
+impl Var {
+    pub fn new(name: Token, initializer: Option<Box<dyn expression::Expression>>) -> Self {
+        Var { name, initializer }
+    }
+}
+
+impl<T> Accept<T> for Var {
+    fn accept(&self, visitor: &mut dyn Visitor<T>) -> T {
+        visitor.visit_var_statement(self)
+    }
+}
+
+// While.
+
+pub struct While {
+    pub condition: Box<dyn expression::Expression>,
+    pub body: Box<dyn Statement>,
+    pub increments: Vec<Box<dyn expression::Expression>>, // From a desugared for loop.
+}
+
+impl While {
+    pub fn new(
+        condition: Box<dyn expression::Expression>,
+        body: Box<dyn Statement>,
+        increments: Vec<Box<dyn expression::Expression>>,
+    ) -> Self {
+        While {
+            condition,
+            body,
+            increments,
+        }
+    }
+}
+
+impl<T> Accept<T> for While {
+    fn accept(&self, visitor: &mut dyn Visitor<T>) -> T {
+        visitor.visit_while_statement(self)
+    }
+}
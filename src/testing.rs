@@ -0,0 +1,154 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::io::{self, Write};
+use std::panic;
+use std::rc::Rc;
+use std::thread;
+
+use crate::diagnostics::Diagnostics;
+use crate::interpreter::Interpreter;
+use crate::parser::Parser;
+use crate::resolver::Resolver;
+use crate::scanner::{Scanner, Token};
+use crate::statement::Statement;
+
+/* Somewhere for a test's interpreter to print to. Clones share the same buffer, so
+one can be handed to the interpreter while the test keeps the other to read from. */
+#[derive(Clone, Default)]
+pub struct Output(Rc<RefCell<Vec<u8>>>);
+
+impl Output {
+    // Everything written so far.
+    pub fn text(&self) -> String {
+        String::from_utf8(self.0.borrow().clone()).expect("Output should be UTF-8.")
+    }
+}
+
+impl Write for Output {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.borrow_mut().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+// Scan the source, returning the tokens along with any errors.
+pub fn scan(source: &str) -> (Vec<Token>, Diagnostics) {
+    let mut diagnostics = Diagnostics::new(String::from("<test>"), source);
+    let tokens = Scanner::new(String::from(source)).scan_tokens(&mut diagnostics);
+    (tokens, diagnostics)
+}
+
+/* Scan, parse and resolve the source, returning the messages of any errors found
+before running in the order they appear. */
+pub fn errors(source: &str) -> Vec<String> {
+    match build(source) {
+        Ok(_) => Vec::new(),
+        Err(messages) => messages,
+    }
+}
+
+// Parse the source, which must not have any errors.
+pub fn parse(source: &str) -> Vec<Box<dyn Statement>> {
+    build(source)
+        .unwrap_or_else(|messages| panic!("Unexpected errors: {:?}", messages))
+        .0
+}
+
+// Run the source in a new interpreter, returning what it printed.
+pub fn run(source: &str) -> String {
+    Session::new().run(source)
+}
+
+// Run the source in a new interpreter, returning the message of the error it stops with.
+pub fn run_error(source: &str) -> String {
+    Session::new().run_error(source)
+}
+
+/* Run the test on a thread with as much stack as the interpreter gets when run from
+the command line, for tests that go as deep as the limits allow. */
+pub fn on_interpreter_stack<F>(test: F)
+where
+    F: FnOnce() + Send + 'static,
+{
+    let runner = thread::Builder::new()
+        .stack_size(crate::STACK_SIZE)
+        .spawn(test)
+        .expect("Failed to start the test thread.");
+    if let Err(error) = runner.join() {
+        panic::resume_unwind(error);
+    }
+}
+
+// An interpreter that keeps its globals between runs, as the prompt does.
+pub struct Session {
+    pub interpreter: Interpreter,
+    output: Output,
+}
+
+impl Session {
+    pub fn new() -> Self {
+        let output = Output::default();
+        Session {
+            interpreter: Interpreter::with_output(Box::new(output.clone())),
+            output,
+        }
+    }
+
+    // Run the source, returning what it printed. Any error fails the test.
+    pub fn run(&mut self, source: &str) -> String {
+        match self.try_run(source) {
+            Ok(printed) => printed,
+            Err(message) => panic!("Unexpected error: {}", message),
+        }
+    }
+
+    // Run the source, returning the message of the error it must stop with.
+    pub fn run_error(&mut self, source: &str) -> String {
+        match self.try_run(source) {
+            Ok(printed) => panic!("Expected an error, but it printed {:?}", printed),
+            Err(message) => message,
+        }
+    }
+
+    // Run the source, returning what it printed or the first error it hit.
+    fn try_run(&mut self, source: &str) -> Result<String, String> {
+        let (statements, locals) = build(source).map_err(|messages| messages.join("\n"))?;
+        self.interpreter.resolve(&locals);
+        let start = self.output.text().len();
+        let result = self.interpreter.interpret(&statements);
+        let printed = self.output.text()[start..].to_string();
+        match result {
+            Ok(()) => Ok(printed),
+            Err(error) => Err(error.message),
+        }
+    }
+}
+
+// The parsed statements and the resolver's depth for each local variable use.
+type Built = (Vec<Box<dyn Statement>>, HashMap<usize, usize>);
+
+fn build(source: &str) -> Result<Built, Vec<String>> {
+    let (tokens, diagnostics) = scan(source);
+    let diagnostics = Rc::new(RefCell::new(diagnostics));
+    let statements = Parser::new(tokens, Rc::clone(&diagnostics)).parse();
+
+    let mut resolver = Resolver::new(Rc::clone(&diagnostics));
+    if !diagnostics.borrow().has_errors() {
+        resolver.resolve(&statements);
+    }
+
+    let diagnostics = diagnostics.borrow();
+    if diagnostics.has_errors() {
+        let messages = diagnostics
+            .sorted()
+            .iter()
+            .map(|diagnostic| diagnostic.message.clone())
+            .collect();
+        return Err(messages);
+    }
+    Ok((statements, resolver.into_locals()))
+}
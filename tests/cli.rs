@@ -0,0 +1,300 @@
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+use std::process::{Command, Output, Stdio};
+
+// Run the interpreter with the arguments, feeding it the input on stdin.
+fn lox(args: &[&str], input: &str) -> Output {
+    lox_with_env(args, input, &[("NO_COLOR", "1")])
+}
+
+// Run the interpreter as lox() does, but with only the given colour settings.
+fn lox_with_env(args: &[&str], input: &str, env: &[(&str, &str)]) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_lox-lang-rust"))
+        .args(args)
+        .env_remove("NO_COLOR")
+        .env_remove("CLICOLOR_FORCE")
+        .envs(env.iter().copied())
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("Failed to start the interpreter.");
+    let mut stdin = child.stdin.take().expect("Failed to open stdin.");
+    stdin
+        .write_all(input.as_bytes())
+        .expect("Failed to write to stdin.");
+    drop(stdin);
+    child
+        .wait_with_output()
+        .expect("Failed to run the interpreter.")
+}
+
+fn stdout(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stdout).into_owned()
+}
+
+#[test]
+fn version_prints_the_crate_version() {
+    let output = lox(&["--version"], "");
+    assert_eq!(output.status.code(), Some(0));
+    assert!(stdout(&output).contains(env!("CARGO_PKG_VERSION")));
+}
+
+#[test]
+fn tokens_flag_prints_tokens_without_running() {
+    let output = lox(&["--tokens", "-"], "print 1;");
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(
+        stdout(&output),
+        "PRINT print nil\nNUMBER 1 1.0\nSEMICOLON ; nil\nEOF  nil\n"
+    );
+}
+
+#[test]
+fn ast_flag_prints_the_tree_without_running() {
+    let output = lox(&["--ast", "-"], "print 1 + 2;");
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(stdout(&output), "(print (+ 1 2))\n");
+}
+
+#[test]
+fn ast_flag_exits_65_on_parse_errors() {
+    let output = lox(&["--ast", "-"], "print 1 +;");
+    assert_eq!(output.status.code(), Some(65));
+    assert!(stdout(&output).contains("Expect expression."));
+}
+
+#[test]
+fn runtime_errors_exit_70() {
+    let output = lox(&["-"], "print -\"a\";");
+    assert_eq!(output.status.code(), Some(70));
+}
+
+#[test]
+fn parse_errors_exit_65() {
+    let output = lox(&["-"], "print 1 +;");
+    assert_eq!(output.status.code(), Some(65));
+}
+
+#[test]
+fn too_many_scripts_exit_64() {
+    let output = lox(&["one.lox", "two.lox"], "");
+    assert_eq!(output.status.code(), Some(64));
+}
+
+#[test]
+fn prompt_keeps_globals_between_lines() {
+    let output = lox(&[], "var x = 1;\nprint x;\nx + 1\n");
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(stdout(&output), "> > 1.0\n> 2.0\n> ");
+}
+
+#[test]
+fn prompt_and_read_line_share_stdin() {
+    let output = lox(&[], "var line = readLine();\nhello\nprint line;\n");
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(stdout(&output), "> > hello\n> ");
+}
+
+#[test]
+fn prompt_exits_at_end_of_input() {
+    let output = lox(&[], "");
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(stdout(&output), "> ");
+}
+
+#[test]
+fn prompt_exits_on_quit() {
+    let output = lox(&[], "quit\nprint 1;\n");
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(stdout(&output), "> ");
+}
+
+#[test]
+fn prompt_reads_until_brackets_balance() {
+    let output = lox(&[], "fun f() {\n  return 2;\n}\nprint f();\n");
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(stdout(&output), "> ... ... > 2.0\n> ");
+}
+
+#[test]
+fn prompt_reads_until_block_comment_ends() {
+    let output = lox(&[], "/* a comment\nprint 1;\n*/ print 2;\n");
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(stdout(&output), "> ... ... 2.0\n> ");
+}
+
+#[test]
+fn prompt_reads_until_interpolation_ends() {
+    let output = lox(&[], "print \"${1 +\n2}\";\n");
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(stdout(&output), "> ... 3.0\n> ");
+}
+
+#[test]
+fn prompt_runs_unbalanced_input_on_blank_line() {
+    let output = lox(&[], "print (1;\n\nprint 2;\n");
+    let printed = stdout(&output);
+    assert!(printed.starts_with("> ... "));
+    assert!(printed.contains("Expect ')' after expression."));
+    assert!(printed.ends_with("> 2.0\n> "));
+}
+
+#[test]
+fn errors_name_the_script() {
+    let path = Path::new(env!("CARGO_TARGET_TMPDIR")).join("named_errors.lox");
+    let path = path.to_str().expect("The path should be UTF-8.");
+    fs::write(path, "print 1 +;").expect("Failed to write the script.");
+    let output = lox(&[path], "");
+    assert!(stdout(&output).starts_with(&format!("{}:1:10: Error at ';'", path)));
+
+    fs::write(path, "\nprint -\"a\";").expect("Failed to write the script.");
+    let output = lox(&[path], "");
+    assert!(stdout(&output).contains(&format!("[{}:2]", path)));
+}
+
+#[test]
+fn prompt_tokens_command() {
+    let output = lox(&[], ".tokens 1+2\n");
+    assert_eq!(
+        stdout(&output),
+        "> NUMBER 1 1.0\nPLUS + nil\nNUMBER 2 2.0\nEOF  nil\n> "
+    );
+}
+
+#[test]
+fn prompt_help_and_unknown_commands() {
+    let output = lox(&[], ".help\n.bogus\nprint 1;\n");
+    let printed = stdout(&output);
+    assert!(printed.contains(".tokens SOURCE  List the tokens scanned from the source.\n"));
+    assert!(printed.contains("Unknown command '.bogus'. Type .help to see the commands.\n"));
+    assert!(printed.ends_with("> 1.0\n> "));
+}
+
+#[test]
+fn prompt_clear_forgets_globals() {
+    let output = lox(&[], "var a = 1;\n.clear\nprint a;\n");
+    assert!(stdout(&output).contains("Undefined variable 'a'."));
+}
+
+#[test]
+fn prompt_history_lists_earlier_input() {
+    let output = lox(&[], "var a = 1;\nfun f() {\n}\n.history\n");
+    let history = "   1  var a = 1;\n   2  fun f() {\n      }\n   3  .history\n";
+    assert_eq!(stdout(&output), format!("> > ... > {}> ", history));
+}
+
+#[test]
+fn dump_env_lists_globals_by_name() {
+    let source = "var b = \"s\"; fun f() {} class C {} var a = nil;";
+    let output = lox(&["--dump-env", "-"], source);
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(
+        stdout(&output),
+        "C = <class C>\na = nil\nb = s\nf = <fn f/0>\n"
+    );
+}
+
+#[test]
+fn dump_env_is_skipped_after_an_error() {
+    let output = lox(&["--dump-env", "-"], "var a = 1; print -\"a\";");
+    assert_eq!(output.status.code(), Some(70));
+    assert!(!stdout(&output).contains("a = 1.0"));
+}
+
+#[test]
+fn max_call_depth_flag_limits_recursion() {
+    let source = "fun f(n) { if (n > 0) f(n - 1); } f(5); print \"ok\"; f(6);";
+    let output = lox(&["--max-call-depth", "6", "-"], source);
+    assert_eq!(output.status.code(), Some(70));
+    assert!(stdout(&output).starts_with("ok\nStack overflow."));
+}
+
+#[test]
+fn max_call_depth_must_be_a_number() {
+    let output = lox(&["--max-call-depth", "lots", "-"], "");
+    assert_eq!(output.status.code(), Some(64));
+    assert_eq!(lox(&["--max-call-depth"], "").status.code(), Some(64));
+}
+
+#[test]
+fn warnings_do_not_change_the_exit_code() {
+    let output = lox(&["-"], "var If = 1; print If;");
+    assert_eq!(output.status.code(), Some(0));
+    let printed = stdout(&output);
+    assert!(printed.contains("Warning: 'If' is an identifier, not the keyword 'if'."));
+    assert!(printed.ends_with("1.0\n"));
+}
+
+#[test]
+fn nil_style_spells_nil_in_output_and_tokens() {
+    let source = "print nil; print [nil];";
+    assert_eq!(stdout(&lox(&["-"], source)), "nil\n[nil]\n");
+    assert_eq!(
+        stdout(&lox(&["--nil-style=nil", "-"], source)),
+        "nil\n[nil]\n"
+    );
+    assert_eq!(
+        stdout(&lox(&["--nil-style=null", "-"], source)),
+        "null\n[null]\n"
+    );
+
+    let output = lox(&["--nil-style=null", "--tokens", "-"], "x");
+    assert_eq!(stdout(&output), "IDENTIFIER x null\nEOF  null\n");
+    let output = lox(&["--tokens", "-"], "x");
+    assert_eq!(stdout(&output), "IDENTIFIER x nil\nEOF  nil\n");
+}
+
+#[test]
+fn prompt_echoes_expressions_with_or_without_semicolon() {
+    let output = lox(&[], "1 + 2\n1 + 2;\nnil\nnil;\n\"a\"\n");
+    assert_eq!(stdout(&output), "> 3.0\n> 3.0\n> nil\n> nil\n> a\n> ");
+}
+
+#[test]
+fn scripts_do_not_echo_expressions() {
+    let output = lox(&["-"], "1 + 2;\n\"a\";");
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(stdout(&output), "");
+}
+
+#[test]
+fn errors_show_the_line_with_a_caret() {
+    let output = lox(&["-"], "var a = 1;\nprint a +;");
+    assert_eq!(
+        stdout(&output),
+        "<stdin>:2:10: Error at ';': Expect expression.\n    print a +;\n             ^\n"
+    );
+}
+
+#[test]
+fn no_color_output_has_no_escape_sequences() {
+    let source = "var If = 1;\nprint 1 +;";
+    let force = [("CLICOLOR_FORCE", "1")];
+    let colored = stdout(&lox_with_env(&["-"], source, &force));
+    assert!(colored.contains("\x1b[1;31mError\x1b[0m"));
+
+    let plain = [
+        lox_with_env(&["--no-color", "-"], source, &force),
+        lox_with_env(
+            &["-"],
+            source,
+            &[("CLICOLOR_FORCE", "1"), ("NO_COLOR", "1")],
+        ),
+        lox_with_env(&["-"], source, &[]),
+    ];
+    for output in &plain {
+        let printed = stdout(output);
+        assert!(printed.contains("Error at ';'"));
+        assert!(printed.contains("Warning"));
+        assert!(!printed.contains('\x1b'));
+    }
+}
+
+#[test]
+fn dash_reads_the_script_from_stdin() {
+    let output = lox(&["-"], "var a = 1;\nprint a + 1;\n");
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(stdout(&output), "2.0\n");
+}